@@ -0,0 +1,181 @@
+//! Atomic types that may not be provided by all targets
+//!
+//! Targets without atomic read-modify-write instructions, such as thumbv6m
+//! and riscv32i, are provided with atomics given the `critical-section`
+//! feature. Their read-modify-write operations are performed as a load and a
+//! store within a critical section.
+
+#[cfg(all(not(target_has_atomic = "ptr"), not(feature = "critical-section")))]
+compile_error!(
+    "This target has no atomic read-modify-write instructions: enable the critical-section feature"
+);
+
+#[cfg(target_has_atomic = "ptr")]
+pub(crate) use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicUsize};
+
+#[cfg(all(not(target_has_atomic = "ptr"), feature = "critical-section"))]
+pub(crate) use critical_section_atomic::{
+    AtomicBool, AtomicIsize, AtomicPtr, AtomicU8, AtomicUsize,
+};
+
+#[cfg(target_has_atomic = "64")]
+pub(crate) use core::sync::atomic::AtomicU64;
+
+#[cfg(all(not(target_has_atomic = "64"), feature = "portable-atomic"))]
+pub(crate) use portable_atomic::AtomicU64;
+
+#[cfg(all(
+    not(target_has_atomic = "64"),
+    not(feature = "portable-atomic"),
+    feature = "critical-section"
+))]
+pub(crate) use critical_section_atomic::AtomicU64;
+
+/// Atomics having only those operations required by the crate, and with
+/// the same signatures as their `core` counterparts.
+#[cfg(feature = "critical-section")]
+#[allow(dead_code)]
+mod critical_section_atomic {
+    use core::{
+        cell::Cell,
+        sync::atomic::{self, Ordering},
+    };
+
+    macro_rules! atomic {
+        ($name:ident $(<$t:ident>)?, $value:ty) => {
+            #[derive(Default)]
+            pub(crate) struct $name$(<$t>)?(atomic::$name$(<$t>)?);
+
+            impl$(<$t>)? $name$(<$t>)? {
+                pub(crate) const fn new(value: $value) -> Self {
+                    Self(atomic::$name::new(value))
+                }
+
+                pub(crate) fn load(&self, order: Ordering) -> $value {
+                    self.0.load(order)
+                }
+
+                pub(crate) fn store(&self, value: $value, order: Ordering) {
+                    self.0.store(value, order)
+                }
+
+                pub(crate) fn swap(&self, value: $value, _order: Ordering) -> $value {
+                    self.update(|_| Some(value)).unwrap_or_else(|prev| prev)
+                }
+
+                pub(crate) fn compare_exchange(
+                    &self,
+                    current: $value,
+                    new: $value,
+                    _success: Ordering,
+                    _failure: Ordering,
+                ) -> Result<$value, $value> {
+                    self.update(|prev| (prev == current).then_some(new))
+                }
+
+                fn update(
+                    &self,
+                    f: impl FnOnce($value) -> Option<$value>,
+                ) -> Result<$value, $value> {
+                    critical_section::with(|_| {
+                        let prev = self.0.load(Ordering::SeqCst);
+                        match f(prev) {
+                            Some(value) => {
+                                self.0.store(value, Ordering::SeqCst);
+                                Ok(prev)
+                            }
+                            None => Err(prev),
+                        }
+                    })
+                }
+            }
+        };
+    }
+
+    macro_rules! atomic_int {
+        ($name:ident, $value:ty) => {
+            atomic!($name, $value);
+
+            impl $name {
+                pub(crate) fn fetch_add(&self, value: $value, _order: Ordering) -> $value {
+                    self.update(|prev| Some(prev.wrapping_add(value)))
+                        .unwrap_or_else(|prev| prev)
+                }
+
+                pub(crate) fn fetch_sub(&self, value: $value, _order: Ordering) -> $value {
+                    self.update(|prev| Some(prev.wrapping_sub(value)))
+                        .unwrap_or_else(|prev| prev)
+                }
+            }
+        };
+    }
+
+    atomic!(AtomicBool, bool);
+    atomic!(AtomicPtr<T>, *mut T);
+    atomic_int!(AtomicU8, u8);
+    atomic_int!(AtomicUsize, usize);
+    atomic_int!(AtomicIsize, isize);
+
+    /// A 64 bit value for targets without 64 bit atomics. As these targets
+    /// may not even have 64 bit loads and stores, the value is always
+    /// accessed within a critical section.
+    pub(crate) struct AtomicU64(critical_section::Mutex<Cell<u64>>);
+
+    impl AtomicU64 {
+        pub(crate) const fn new(value: u64) -> Self {
+            Self(critical_section::Mutex::new(Cell::new(value)))
+        }
+
+        pub(crate) fn fetch_add(&self, value: u64, _order: Ordering) -> u64 {
+            critical_section::with(|cs| {
+                let cell = self.0.borrow(cs);
+                let prev = cell.get();
+                cell.set(prev.wrapping_add(value));
+                prev
+            })
+        }
+
+        pub(crate) fn load(&self, _order: Ordering) -> u64 {
+            critical_section::with(|cs| self.0.borrow(cs).get())
+        }
+
+        pub(crate) fn store(&self, value: u64, _order: Ordering) {
+            critical_section::with(|cs| self.0.borrow(cs).set(value))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn read_modify_write() {
+            let value = AtomicUsize::new(usize::MAX);
+            assert_eq!(value.fetch_add(2, Ordering::Relaxed), usize::MAX);
+            assert_eq!(value.fetch_sub(1, Ordering::Relaxed), 1);
+            assert_eq!(value.swap(5, Ordering::Relaxed), 0);
+            assert_eq!(
+                value.compare_exchange(4, 6, Ordering::Relaxed, Ordering::Relaxed),
+                Err(5)
+            );
+            assert_eq!(
+                value.compare_exchange(5, 6, Ordering::Relaxed, Ordering::Relaxed),
+                Ok(5)
+            );
+            assert_eq!(value.load(Ordering::Relaxed), 6);
+
+            let flag = AtomicBool::new(false);
+            assert!(!flag.swap(true, Ordering::Relaxed));
+            assert!(flag.swap(true, Ordering::Relaxed));
+        }
+
+        #[test]
+        fn u64_beyond_32_bits() {
+            let value = AtomicU64::new(u32::MAX as u64);
+            assert_eq!(value.fetch_add(2, Ordering::Relaxed), u32::MAX as u64);
+            assert_eq!(value.load(Ordering::Relaxed), u32::MAX as u64 + 2);
+            value.store(0, Ordering::Relaxed);
+            assert_eq!(value.load(Ordering::Relaxed), 0);
+        }
+    }
+}
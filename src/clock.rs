@@ -0,0 +1,94 @@
+//! Clocks provide the time at which metrics are created so that a consumer
+//! can detect when a metric has been reset.
+//!
+//! Targets without a standard library supply their own clock e.g. given a
+//! real-time clock peripheral. With the `std` feature, `SystemClock` may be
+//! used.
+
+use core::sync::atomic::Ordering;
+
+use crate::{
+    atomic::AtomicBool,
+    seqlock::{Halves, SeqLock},
+};
+
+/// A source of the current time in milliseconds since the Unix epoch.
+pub trait Clock: Sync {
+    fn now(&self) -> u64;
+}
+
+/// The system's clock.
+#[cfg(feature = "std")]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
+
+/// An optional timestamp that can be read and written on targets with only
+/// word-sized atomics. Neither reading nor writing ever waits: a write while
+/// another is in progress is dropped, and there is no timestamp to read while
+/// one is being written.
+#[derive(Default)]
+pub(crate) struct AtomicTimestamp {
+    lock: SeqLock,
+    present: AtomicBool,
+    timestamp: Halves,
+}
+
+impl AtomicTimestamp {
+    pub(crate) const fn new() -> Self {
+        Self {
+            lock: SeqLock::new(),
+            present: AtomicBool::new(false),
+            timestamp: Halves::new(),
+        }
+    }
+
+    pub(crate) fn store(&self, timestamp: Option<u64>) {
+        self.lock.write(|| {
+            self.present.store(timestamp.is_some(), Ordering::Relaxed);
+            self.timestamp.store(timestamp.unwrap_or_default());
+        });
+    }
+
+    pub(crate) fn load(&self) -> Option<u64> {
+        self.lock
+            .read(|| {
+                self.present
+                    .load(Ordering::Relaxed)
+                    .then(|| self.timestamp.load())
+            })
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp() {
+        let timestamp = AtomicTimestamp::new();
+        assert_eq!(timestamp.load(), None);
+        timestamp.store(Some(u32::MAX as u64 + 1));
+        assert_eq!(timestamp.load(), Some(u32::MAX as u64 + 1));
+        timestamp.store(None);
+        assert_eq!(timestamp.load(), None);
+        timestamp.store(Some(1));
+        assert_eq!(timestamp.load(), Some(1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn system_clock() {
+        // 2022-01-01T00:00:00Z
+        assert!(SystemClock.now() > 1_640_995_200_000);
+    }
+}
@@ -1,3 +1,88 @@
 //! Encoders take care of serializing a metric into another form
 //!
 pub mod text;
+
+use crate::Error;
+
+/// A sink receives the bytes produced by an encoder. Sinks are supplied
+/// by the caller so that encoders need not allocate.
+pub trait Sink {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error>;
+    /// The number of bytes written
+    fn position(&self) -> usize;
+    /// Discard bytes written beyond a given position
+    fn truncate(&mut self, position: usize);
+}
+
+/// A sink that writes into a fixed-size buffer. A write that does not
+/// fit within the remainder of the buffer is rejected in its entirety
+/// with [Error::BufferFull].
+pub struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// The number of bytes written
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if nothing has been written
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bytes written
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Discard everything written so that the buffer may be reused
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Sink for SliceSink<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.len + bytes.len();
+        let dest = self.buf.get_mut(self.len..end).ok_or(Error::BufferFull)?;
+        dest.copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn position(&self) -> usize {
+        self.len
+    }
+
+    fn truncate(&mut self, position: usize) {
+        self.len = self.len.min(position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slice_sink() {
+        let mut buf = [0; 8];
+        let mut sink = SliceSink::new(&mut buf);
+        assert!(sink.is_empty());
+        assert_eq!(sink.write(b"hello"), Ok(()));
+        assert_eq!(sink.write(b"world"), Err(Error::BufferFull));
+        assert_eq!(sink.write(b"!"), Ok(()));
+        assert_eq!(sink.len(), 6);
+        assert_eq!(sink.written(), b"hello!");
+        sink.truncate(2);
+        assert_eq!(sink.written(), b"he");
+        sink.clear();
+        assert!(sink.is_empty());
+    }
+}
@@ -1,23 +1,469 @@
 //! The Prometheus text encoder adopted by OpenMetrics
 
-use crate::{metrics::counter::Counter, Encoder, Metric};
+use crate::{Encoder, Error, Label, LabelValue, MetricDesc, MetricType, Namespace, Sample, Value};
 
-pub struct TextEncoder;
+use super::Sink;
 
-impl Encoder for TextEncoder {
-    fn write_desc(&mut self, _desc: &crate::MetricDesc)
-    where
-        Self: Sized,
-    {
-        // TODO
+/// Encodes metrics in the OpenMetrics text exposition format,
+/// writing the bytes out to a sink.
+pub struct TextEncoder<'a, S> {
+    sink: S,
+    desc: Option<&'a MetricDesc<'a>>,
+    namespace: Option<&'a Namespace<'a>>,
+    constant_labels: &'a [Label<'a>],
+}
+
+impl<'a, S> TextEncoder<'a, S>
+where
+    S: Sink,
+{
+    pub const fn new(sink: S) -> Self {
+        Self {
+            sink,
+            desc: None,
+            namespace: None,
+            constant_labels: &[],
+        }
+    }
+
+    /// Return a reference to the sink
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Return a mutable reference to the sink e.g. to drain it
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Return the sink
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    /// Write a label value or help text, escaping backslashes, line feeds
+    /// and double quotes
+    fn write_escaped(&mut self, s: &str) -> Result<(), Error> {
+        let mut start = 0;
+        for (i, b) in s.bytes().enumerate() {
+            let escaped: &[u8] = match b {
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'"' => b"\\\"",
+                _ => continue,
+            };
+            self.sink.write(&s.as_bytes()[start..i])?;
+            self.sink.write(escaped)?;
+            start = i + 1;
+        }
+        self.sink.write(&s.as_bytes()[start..])
+    }
+
+    fn write_unsigned(&mut self, value: u64) -> Result<(), Error> {
+        self.write_digits(value, 1)
+    }
+
+    /// Write the digits of a value, padded with leading zeros to a width.
+    fn write_digits(&mut self, value: u64, width: usize) -> Result<(), Error> {
+        let mut buf = [b'0'; 20];
+        let mut i = buf.len();
+        let mut value = value;
+        loop {
+            i -= 1;
+            buf[i] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        for _ in buf.len()..width {
+            self.sink.write(b"0")?;
+        }
+        self.sink
+            .write(&buf[i.min(buf.len().saturating_sub(width))..])
+    }
+
+    fn write_signed(&mut self, value: i64) -> Result<(), Error> {
+        if value < 0 {
+            self.sink.write(b"-")?;
+        }
+        self.write_unsigned(value.unsigned_abs())
+    }
+
+    fn write_decimal(&mut self, value: i64, places: u8) -> Result<(), Error> {
+        if places == 0 {
+            return self.write_signed(value);
+        }
+        if value < 0 {
+            self.sink.write(b"-")?;
+        }
+        // Beyond 19 places, the scale exceeds any value
+        let value = value.unsigned_abs();
+        let (whole, fraction) = match 10u64.checked_pow(places.into()) {
+            Some(scale) => (value / scale, value % scale),
+            None => (0, value),
+        };
+        self.write_unsigned(whole)?;
+        self.sink.write(b".")?;
+        self.write_digits(fraction, places.into())
+    }
+
+    fn write_hex(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        for b in bytes {
+            self.sink
+                .write(&[DIGITS[(b >> 4) as usize], DIGITS[(b & 0xf) as usize]])?;
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, value: Value) -> Result<(), Error> {
+        match value {
+            Value::Unsigned(v) => self.write_unsigned(v),
+            Value::Signed(v) => self.write_signed(v),
+            Value::Decimal { value, places } => self.write_decimal(value, places),
+        }
+    }
+
+    fn write_label(&mut self, name: &str, value: &LabelValue) -> Result<(), Error> {
+        self.sink.write(name.as_bytes())?;
+        self.write_label_value(value)
     }
-    fn write(&mut self, _bytes: &[u8]) {
-        // TODO
+
+    fn write_label_value(&mut self, value: &LabelValue) -> Result<(), Error> {
+        self.sink.write(b"=\"")?;
+        match *value {
+            LabelValue::Str(s) => self.write_escaped(s)?,
+            LabelValue::Unsigned(v) => self.write_unsigned(v)?,
+            LabelValue::Signed(v) => self.write_signed(v)?,
+            LabelValue::Decimal { value, places } => self.write_decimal(value, places)?,
+            LabelValue::Hex(bytes) => self.write_hex(bytes)?,
+        }
+        self.sink.write(b"\"")
+    }
+
+    /// Write a metric's name, prefixed by that of its namespace
+    fn write_name(&mut self, name: &str) -> Result<(), Error> {
+        if let Some(namespace) = self.namespace {
+            self.sink.write(namespace.prefix().as_bytes())?;
+        }
+        self.sink.write(name.as_bytes())
+    }
+
+    fn write_metadata(&mut self, keyword: &[u8], name: &str) -> Result<(), Error> {
+        self.sink.write(b"# ")?;
+        self.sink.write(keyword)?;
+        self.sink.write(b" ")?;
+        self.write_name(name)?;
+        self.sink.write(b" ")
     }
 }
 
-impl Metric for Counter {
-    fn encode(&self, _enc: &mut dyn Encoder) {
-        // TODO
+impl<'a, S> Encoder<'a> for TextEncoder<'a, S>
+where
+    S: Sink,
+{
+    fn write_desc(&mut self, desc: &'a MetricDesc<'a>) -> Result<(), Error> {
+        self.desc = Some(desc);
+        self.namespace = desc.namespace();
+
+        self.write_metadata(b"TYPE", desc.name)?;
+        self.sink.write(match desc.metric_type() {
+            MetricType::Counter => b"counter\n",
+            MetricType::Gauge => b"gauge\n",
+            MetricType::Histogram => b"histogram\n",
+            MetricType::GaugeHistogram => b"gaugehistogram\n",
+            MetricType::Summary => b"summary\n",
+            MetricType::Info => b"info\n",
+            MetricType::StateSet => b"stateset\n",
+        })?;
+
+        self.write_metadata(b"HELP", desc.name)?;
+        self.write_escaped(desc.help)?;
+        self.sink.write(b"\n")?;
+
+        if let Some(unit) = desc.unit {
+            self.write_metadata(b"UNIT", desc.name)?;
+            self.sink.write(unit.as_bytes())?;
+            self.sink.write(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> Result<(), Error> {
+        if let Some(desc) = self.desc {
+            self.write_name(desc.name)?;
+        }
+        self.sink.write(sample.suffix.as_bytes())?;
+
+        let namespace_labels = self
+            .namespace
+            .map(|namespace| namespace.labels())
+            .unwrap_or_default();
+        let label_names = self.desc.map(|desc| desc.labels).unwrap_or_default();
+        let labels = self
+            .constant_labels
+            .iter()
+            .chain(namespace_labels)
+            .map(|label| (label.name, &label.value))
+            .chain(
+                label_names
+                    .iter()
+                    .zip(sample.label_values)
+                    .map(|(name, value)| (*name, value)),
+            )
+            .chain(sample.labels.iter().map(|label| (label.name, &label.value)));
+        let mut labelled = false;
+        for (name, value) in labels {
+            self.sink.write(if labelled { b"," } else { b"{" })?;
+            self.write_label(name, value)?;
+            labelled = true;
+        }
+        if let Some(value) = &sample.name_label {
+            let desc = self
+                .desc
+                .filter(|desc| desc.is_label_name())
+                .ok_or(Error::InvalidLabelName)?;
+            self.sink.write(if labelled { b"," } else { b"{" })?;
+            self.write_name(desc.name)?;
+            self.write_label_value(value)?;
+            labelled = true;
+        }
+        if labelled {
+            self.sink.write(b"}")?;
+        }
+
+        self.sink.write(b" ")?;
+        self.write_value(sample.value)?;
+
+        if let Some(exemplar) = sample.exemplar {
+            self.sink.write(b" # {")?;
+            for (i, label) in exemplar.labels.iter().enumerate() {
+                if i > 0 {
+                    self.sink.write(b",")?;
+                }
+                self.write_label(label.name, &label.value)?;
+            }
+            self.sink.write(b"} ")?;
+            self.write_value(exemplar.value)?;
+            if let Some(timestamp) = exemplar.timestamp {
+                self.sink.write(b" ")?;
+                self.write_decimal(timestamp as i64, 3)?;
+            }
+        }
+        self.sink.write(b"\n")
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.sink.write(bytes)
+    }
+
+    fn write_eof(&mut self) -> Result<(), Error> {
+        self.sink.write(b"# EOF\n")
+    }
+
+    fn position(&self) -> usize {
+        self.sink.position()
+    }
+
+    fn rewind(&mut self, position: usize) {
+        self.sink.truncate(position);
+    }
+
+    fn set_constant_labels(&mut self, labels: &'a [Label<'a>]) {
+        self.constant_labels = labels;
+    }
+}
+
+/// Encode a descriptor and its metric's samples as text
+#[cfg(test)]
+pub(crate) fn encode_desc<'a>(desc: &'a MetricDesc<'a>) -> String {
+    let mut buf = [0; 1024];
+    let mut encoder = TextEncoder::new(super::SliceSink::new(&mut buf));
+    encoder.write_desc(desc).unwrap();
+    desc.metric.encode(&mut encoder).unwrap();
+    String::from_utf8(encoder.into_inner().written().to_vec()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encoders::SliceSink,
+        metrics::{counter::Counter, gauge::Gauge, histogram::Histogram},
+        Cursor, Registry,
+    };
+
+    use super::*;
+
+    #[test]
+    fn exposition() {
+        static REGISTRY: Registry = Registry::new();
+
+        static REQUESTS: Counter = Counter::new();
+        static REQUESTS_DESC: MetricDesc = MetricDesc::new(
+            "requests",
+            "Requests received\nsince \\boot \"up\"",
+            None,
+            &[],
+            &REQUESTS,
+        );
+
+        static TEMPERATURE: Gauge = Gauge::new();
+        static TEMPERATURE_DESC: MetricDesc = MetricDesc::new(
+            "temperature_celsius",
+            "Board temperature",
+            Some("celsius"),
+            &[],
+            &TEMPERATURE,
+        );
+
+        static LATENCY: Histogram<2> = Histogram::new(&[10, 100]);
+        static LATENCY_DESC: MetricDesc =
+            MetricDesc::new("latency", "Request latency", None, &[], &LATENCY);
+
+        REGISTRY.register(&REQUESTS_DESC).unwrap();
+        REGISTRY.register(&TEMPERATURE_DESC).unwrap();
+        REGISTRY.register(&LATENCY_DESC).unwrap();
+
+        REQUESTS.inc_by(3);
+        TEMPERATURE.set(-5);
+        LATENCY.observe(7);
+        LATENCY.observe(700);
+
+        let mut buf = [0; 1024];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        assert_eq!(REGISTRY.encode(&mut encoder), Ok(()));
+
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE latency histogram
+# HELP latency Request latency
+latency_bucket{le="10"} 1
+latency_bucket{le="100"} 1
+latency_bucket{le="+Inf"} 2
+latency_sum 707
+latency_count 2
+# TYPE temperature_celsius gauge
+# HELP temperature_celsius Board temperature
+# UNIT temperature_celsius celsius
+temperature_celsius -5
+# TYPE requests counter
+# HELP requests Requests received\nsince \\boot \"up\"
+requests_total 3
+# EOF
+"#
+        );
+    }
+
+    #[test]
+    fn escaped_label_values() {
+        let mut buf = [0; 64];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        let labels = [
+            crate::Label::new("path", LabelValue::Str("a\"b\\c\nd")),
+            crate::Label::new("offset", LabelValue::Signed(-1)),
+        ];
+        encoder
+            .write_sample(&Sample::new("x", Value::Unsigned(0)).with_labels(&labels))
+            .unwrap();
+
+        assert_eq!(
+            encoder.into_inner().written(),
+            b"x{path=\"a\\\"b\\\\c\\nd\",offset=\"-1\"} 0\n"
+        );
+    }
+
+    #[test]
+    fn decimal_values() {
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        for (value, places) in [
+            (3300, 3),
+            (-5, 3),
+            (-1500, 3),
+            (0, 2),
+            (42, 0),
+            (i64::MIN, 19),
+            (i64::MAX, 20),
+            (-5, 22),
+        ] {
+            encoder
+                .write_sample(&Sample::new("x", Value::Decimal { value, places }))
+                .unwrap();
+        }
+
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            "x 3.300\nx -0.005\nx -1.500\nx 0.00\nx 42\nx -0.9223372036854775808\n\
+             x 0.09223372036854775807\nx -0.0000000000000000000005\n"
+        );
+    }
+
+    #[test]
+    fn buffer_full() {
+        static REGISTRY: Registry = Registry::new();
+
+        static REQUESTS: Counter = Counter::new();
+        static REQUESTS_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests received", None, &[], &REQUESTS);
+
+        REGISTRY.register(&REQUESTS_DESC).unwrap();
+
+        let mut buf = [0; 32];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        assert_eq!(REGISTRY.encode(&mut encoder), Err(Error::BufferFull));
+
+        let sink = encoder.into_inner();
+        assert_eq!(sink.written(), b"# TYPE requests counter\n# HELP ");
+        assert_eq!(sink.len(), 31);
+    }
+
+    #[test]
+    fn resumable() {
+        static REGISTRY: Registry = Registry::new();
+
+        static REQUESTS: Counter = Counter::new();
+        static REQUESTS_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests received", None, &[], &REQUESTS);
+
+        static TEMPERATURE: Gauge = Gauge::new();
+        static TEMPERATURE_DESC: MetricDesc =
+            MetricDesc::new("temperature", "Board temperature", None, &[], &TEMPERATURE);
+
+        static LATENCY: Histogram<2> = Histogram::new(&[10, 100]);
+        static LATENCY_DESC: MetricDesc =
+            MetricDesc::new("latency", "Request latency", None, &[], &LATENCY);
+
+        REGISTRY.register(&REQUESTS_DESC).unwrap();
+        REGISTRY.register(&TEMPERATURE_DESC).unwrap();
+        REGISTRY.register(&LATENCY_DESC).unwrap();
+
+        let mut buf = [0; 1024];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        let expected = encoder.into_inner().written().to_vec();
+
+        let mut buf = [0; 200];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        let mut cursor = Cursor::new();
+        let mut chunks = Vec::new();
+        while !cursor.is_done() {
+            REGISTRY.encode_from(&mut cursor, &mut encoder).unwrap();
+            chunks.push(encoder.sink().written().to_vec());
+            encoder.sink_mut().clear();
+        }
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), expected);
+
+        let mut buf = [0; 16];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        let mut cursor = Cursor::new();
+        assert_eq!(
+            REGISTRY.encode_from(&mut cursor, &mut encoder),
+            Err(Error::BufferFull)
+        );
+        assert!(encoder.sink().is_empty());
+        assert!(!cursor.is_done());
     }
 }
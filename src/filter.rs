@@ -0,0 +1,136 @@
+//! Filtering of the metrics being encoded, so that different consumers
+//! may be given different selections of metrics from the one registry
+//! e.g. a low-bandwidth uplink being given only its counters, while a
+//! local debug port is given everything. See [Registry::encode_filtered].
+//!
+//! [Registry::encode_filtered]: crate::Registry::encode_filtered
+
+use crate::{MetricDesc, MetricType};
+
+/// Selects the metrics to be encoded
+pub enum Filter<'f> {
+    /// Metrics whose names begin with a prefix, including that of any
+    /// namespace
+    Prefix(&'f str),
+    /// Metrics of a type
+    Type(MetricType),
+    /// Metrics for which a function returns true
+    Predicate(&'f dyn Fn(&MetricDesc) -> bool),
+}
+
+impl Filter<'_> {
+    /// True if a metric is selected by the filter
+    pub fn matches(&self, desc: &MetricDesc) -> bool {
+        match self {
+            Filter::Prefix(prefix) => desc
+                .prefix()
+                .bytes()
+                .chain(desc.name.bytes())
+                .take(prefix.len())
+                .eq(prefix.bytes()),
+            Filter::Type(metric_type) => desc.metric_type() == *metric_type,
+            Filter::Predicate(predicate) => predicate(desc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        clock::Clock,
+        encoders::{text::TextEncoder, SliceSink},
+        metrics::{counter::Counter, gauge::Gauge},
+        CompositeRegistry, Cursor, Namespace, Registry,
+    };
+
+    use super::*;
+
+    #[test]
+    fn selections() {
+        struct FixedClock;
+        impl Clock for FixedClock {
+            fn now(&self) -> u64 {
+                1_000
+            }
+        }
+
+        static REGISTRY: Registry = Registry::new().with_clock(&FixedClock);
+        static NET: Namespace = Namespace::new(&REGISTRY, "net_");
+
+        static PACKETS: Counter = Counter::new();
+        static PACKETS_DESC: MetricDesc =
+            MetricDesc::new("packets", "Packets received", None, &[], &PACKETS);
+
+        static TEMPERATURE: Gauge = Gauge::new();
+        static TEMPERATURE_DESC: MetricDesc = MetricDesc::new(
+            "temperature_celsius",
+            "Board temperature",
+            Some("celsius"),
+            &[],
+            &TEMPERATURE,
+        );
+
+        NET.register(&PACKETS_DESC).unwrap();
+        REGISTRY.register(&TEMPERATURE_DESC).unwrap();
+
+        // Metrics not selected are not stamped when encoded
+        PACKETS.reset();
+
+        let packets = "# TYPE net_packets counter\n\
+                       # HELP net_packets Packets received\n\
+                       net_packets_total 0\n\
+                       net_packets_created 1.000\n";
+        let temperature = "# TYPE temperature_celsius gauge\n\
+                           # HELP temperature_celsius Board temperature\n\
+                           # UNIT temperature_celsius celsius\n\
+                           temperature_celsius 0\n";
+
+        let has_unit = |desc: &MetricDesc| desc.unit.is_some();
+        for (filter, expected) in [
+            (Filter::Prefix("net_packets_"), ""),
+            (Filter::Type(MetricType::Gauge), temperature),
+            (Filter::Predicate(&has_unit), temperature),
+        ] {
+            let mut buf = [0; 256];
+            let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+            REGISTRY.encode_filtered(&filter, &mut encoder).unwrap();
+            assert_eq!(
+                core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+                format!("{expected}# EOF\n")
+            );
+        }
+        assert_eq!(PACKETS.created(), None);
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY
+            .encode_filtered(&Filter::Prefix("net_"), &mut encoder)
+            .unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            format!("{packets}# EOF\n")
+        );
+
+        let mut buf = [0; 128];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        let mut cursor = Cursor::new();
+        let filter = Filter::Type(MetricType::Counter);
+        REGISTRY
+            .encode_filtered_from(&filter, &mut cursor, &mut encoder)
+            .unwrap();
+        assert!(cursor.is_done());
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            format!("{packets}# EOF\n")
+        );
+
+        static COMPOSITE: CompositeRegistry = CompositeRegistry::new(&[&REGISTRY]);
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        COMPOSITE.encode_filtered(&filter, &mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            format!("{packets}# EOF\n")
+        );
+    }
+}
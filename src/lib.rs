@@ -1,20 +1,138 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
+
 use core::{
+    any::Any,
+    cell::UnsafeCell,
+    ops::Deref,
     ptr::{self, NonNull},
-    sync::atomic::{AtomicPtr, Ordering},
+    sync::atomic::Ordering,
 };
 
+use atomic::{AtomicBool, AtomicPtr, AtomicU8};
+use clock::Clock;
+use filter::Filter;
+
+mod atomic;
+pub mod clock;
 pub mod encoders;
+pub mod filter;
+mod macros;
 pub mod metrics;
+mod seqlock;
+
+#[cfg(feature = "linkme")]
+#[doc(hidden)]
+pub use linkme;
+
+/// The descriptors of metrics declared anywhere within the binary,
+/// including within dependencies, collected together by the linker.
+/// See [Registry::linked].
+///
+/// Bare metal targets must retain the `linkme_METRICS` section within
+/// their linker script, naming the output section the same so that the
+/// linker provides symbols for its start and end, e.g.:
+///
+/// ```text
+/// linkme_METRICS : { KEEP(*(linkme_METRICS)) } > FLASH
+/// linkm2_METRICS : { KEEP(*(linkm2_METRICS)) } > FLASH
+/// ```
+#[cfg(feature = "linkme")]
+#[linkme::distributed_slice]
+pub static METRICS: [&'static MetricDesc<'static>];
+
+/// Errors that may occur when describing, registering or encoding metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// There is no room left in the output for what is being written.
+    BufferFull,
+    /// A metric name is not of the form `[a-zA-Z_:][a-zA-Z0-9_:]*`.
+    InvalidName,
+    /// A metric name ends with a suffix reserved for samples, such as
+    /// `_total`.
+    ReservedSuffix,
+    /// A label name is not of the form `[a-zA-Z_][a-zA-Z0-9_]*`, or begins
+    /// with the reserved `__`. The names `le` and `quantile` are also
+    /// reserved for constant labels, as histograms and summaries label
+    /// their samples with them. The states of a state set are labelled by
+    /// its metric's name, which therefore may not contain colons, and
+    /// must be known to the encoder.
+    InvalidLabelName,
+    /// A unit is empty, or is not the suffix of its metric's name
+    /// following an underscore.
+    InvalidUnit,
+    /// A descriptor is registered already.
+    AlreadyRegistered,
+    /// A descriptor has been registered with another registry.
+    RegisteredElsewhere,
+    /// Another descriptor of the same name is registered.
+    DuplicateName,
+    /// Another descriptor of the same name is registered, and differs in
+    /// its type or unit.
+    ConflictingFamily,
+    /// A registry's constant labels are set already.
+    LabelsAlreadySet,
+    /// A registry's constant labels exceed the room of its storage.
+    LabelsTooLarge,
+    /// A registry has no storage for constant labels, as given by
+    /// [Registry::with_label_storage].
+    NoLabelStorage,
+    /// A label name is used more than once for the same samples, be it by
+    /// a registry's constant labels, a namespace's, or a descriptor's.
+    DuplicateLabelName,
+}
+
+impl Error {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Error::BufferFull => "buffer full",
+            Error::InvalidName => "invalid metric name",
+            Error::ReservedSuffix => "metric name ends with a reserved suffix",
+            Error::InvalidLabelName => "invalid label name",
+            Error::InvalidUnit => "invalid unit",
+            Error::AlreadyRegistered => "metric is registered already",
+            Error::RegisteredElsewhere => "metric has been registered with another registry",
+            Error::DuplicateName => "metric name is registered already",
+            Error::ConflictingFamily => {
+                "metric name is registered already with a different type or unit"
+            }
+            Error::LabelsAlreadySet => "constant labels are set already",
+            Error::LabelsTooLarge => "constant labels exceed their storage",
+            Error::NoLabelStorage => "no storage for constant labels",
+            Error::DuplicateLabelName => "label name is used more than once",
+        }
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 /// An encoder encodes metrics into bytes.
-pub trait Encoder {
+///
+/// A descriptor is written before its metric's samples, and so an encoder may
+/// retain the descriptor for the purposes of writing those samples.
+pub trait Encoder<'a> {
     /// Writes out the descriptor of a metric.
-    fn write_desc(&mut self, desc: &MetricDesc);
+    fn write_desc(&mut self, desc: &'a MetricDesc<'a>) -> Result<(), Error>;
+    /// Called by a metric to write out one of its samples.
+    fn write_sample(&mut self, sample: &Sample) -> Result<(), Error>;
     /// Called by a metric to encode itself.
-    fn write(&mut self, bytes: &[u8]);
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error>;
+    /// Writes out the end of the metrics.
+    fn write_eof(&mut self) -> Result<(), Error>;
+    /// Returns the position of the output written so far.
+    fn position(&self) -> usize;
+    /// Discards any output written beyond a position previously returned.
+    fn rewind(&mut self, position: usize);
+    /// Attaches labels to every sample that follows, such as the constant
+    /// labels of a registry.
+    fn set_constant_labels(&mut self, _labels: &'a [Label<'a>]) {}
 }
 
 /// From OpenMetrics:
@@ -24,14 +142,188 @@ pub trait Encoder {
 /// They are distinct from logs or events, which focus on records or
 /// information about individual events.
 pub trait Metric {
+    /// The type of metric as per OpenMetrics.
+    fn metric_type(&self) -> MetricType;
     /// Encode this metric into a form expected by a given Encoder.
-    fn encode(&self, enc: &mut dyn Encoder);
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error>;
+    /// Called with the current time, in milliseconds since the Unix epoch,
+    /// by a registry with a [Clock] when the metric is registered and
+    /// before it is encoded. A metric that records its creation time,
+    /// such as a counter, does so unless it has one already.
+    fn stamp(&self, _now: u64) {}
+    /// The metric as [Any], so that it may be accessed as its own type
+    /// via [MetricDesc::metric_as]. Metrics that borrow provide None.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+    /// True if the metric itself labels its samples with the given label
+    /// name, such as the `le` of a histogram's buckets, so that no other
+    /// label of its samples may have that name.
+    fn has_label(&self, _name: &str) -> bool {
+        false
+    }
 }
 
 /// Enumerates the types of metrics as per OpenMetrics and what we
 /// support
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MetricType {
     Counter,
+    Gauge,
+    Histogram,
+    GaugeHistogram,
+    Summary,
+    Info,
+    StateSet,
+}
+
+/// The value of a sample. Values are integral, or fixed-point, so that
+/// targets without floating point support can encode them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    /// A fixed-point value of `value / 10^places`
+    Decimal {
+        value: i64,
+        places: u8,
+    },
+}
+
+/// The value of a label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelValue<'a> {
+    Str(&'a str),
+    Unsigned(u64),
+    Signed(i64),
+    /// A fixed-point value as per [Value::Decimal]
+    Decimal {
+        value: i64,
+        places: u8,
+    },
+    /// Bytes, such as a trace id, encoded as lowercase hexadecimal
+    Hex(&'a [u8]),
+}
+
+impl<'a> From<&'a str> for LabelValue<'a> {
+    fn from(value: &'a str) -> Self {
+        LabelValue::Str(value)
+    }
+}
+
+macro_rules! label_value_from {
+    ($variant:ident, $repr:ty, $($t:ty),*) => {
+        $(
+            impl From<$t> for LabelValue<'_> {
+                fn from(value: $t) -> Self {
+                    LabelValue::$variant(value as $repr)
+                }
+            }
+        )*
+    };
+}
+
+label_value_from!(Unsigned, u64, u8, u16, u32, u64, usize);
+label_value_from!(Signed, i64, i8, i16, i32, i64, isize);
+
+/// A label is a name and value pair distinguishing one sample
+/// from another. A label with an empty name is named after the
+/// metric's family by the encoder, as for the states of a state set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Label<'a> {
+    pub name: &'a str,
+    pub value: LabelValue<'a>,
+}
+
+impl<'a> Label<'a> {
+    pub const fn new(name: &'a str, value: LabelValue<'a>) -> Self {
+        Self { name, value }
+    }
+}
+
+/// A sample is a single value of a metric. Some metrics, such as counters,
+/// are represented by a sample with a suffix applied to the metric's name.
+/// Others, such as histograms, are represented by several samples
+/// distinguished by their suffix and labels.
+///
+/// Label values correspond, in order, with the label names of the
+/// metric's descriptor. Labels are in addition to those and are
+/// specific to the sample, as is any label named after the metric
+/// itself, such as that of a state set's states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample<'a> {
+    pub suffix: &'a str,
+    pub label_values: &'a [LabelValue<'a>],
+    pub labels: &'a [Label<'a>],
+    pub name_label: Option<LabelValue<'a>>,
+    pub value: Value,
+    pub exemplar: Option<Exemplar<'a>>,
+}
+
+impl<'a> Sample<'a> {
+    pub const fn new(suffix: &'a str, value: Value) -> Self {
+        Self {
+            suffix,
+            label_values: &[],
+            labels: &[],
+            name_label: None,
+            value,
+            exemplar: None,
+        }
+    }
+
+    /// Associate values for the descriptor's label names with the sample
+    pub const fn with_label_values(self, label_values: &'a [LabelValue<'a>]) -> Self {
+        Self {
+            label_values,
+            ..self
+        }
+    }
+
+    /// Associate labels with the sample
+    pub const fn with_labels(self, labels: &'a [Label<'a>]) -> Self {
+        Self { labels, ..self }
+    }
+
+    /// Associate a value with the sample for a label named after the
+    /// metric, prefixed by any namespace, which must then have no colons
+    pub const fn with_name_label(self, value: LabelValue<'a>) -> Self {
+        Self {
+            name_label: Some(value),
+            ..self
+        }
+    }
+
+    /// Associate an exemplar with the sample
+    pub const fn with_exemplar(self, exemplar: Exemplar<'a>) -> Self {
+        Self {
+            exemplar: Some(exemplar),
+            ..self
+        }
+    }
+}
+
+/// From OpenMetrics:
+///
+/// Exemplars are references to data outside of the MetricSet. A common use
+/// case are IDs of program traces.
+///
+/// Timestamps are in milliseconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exemplar<'a> {
+    pub labels: &'a [Label<'a>],
+    pub value: Value,
+    pub timestamp: Option<u64>,
+}
+
+impl<'a> Exemplar<'a> {
+    pub const fn new(labels: &'a [Label<'a>], value: Value, timestamp: Option<u64>) -> Self {
+        Self {
+            labels,
+            value,
+            timestamp,
+        }
+    }
 }
 
 /// A metric descriptor exists for the purposes of registering a metric,
@@ -44,9 +336,34 @@ pub struct MetricDesc<'a> {
 
     metric: &'a (dyn Metric + Sync),
     next: AtomicPtr<MetricDesc<'a>>,
+    state: AtomicU8,
+    registry: AtomicPtr<Registry<'a>>,
+    namespace: AtomicPtr<Namespace<'a>>,
 }
 
+/// The states of a descriptor with respect to the registry it is linked
+/// into. Once linked, a descriptor remains so for the life of the
+/// registry, and is encoded only while registered. Descriptors of a
+/// section are rejected if another earlier in the section has the same
+/// name.
+const IDLE: u8 = 0;
+const CLAIMED: u8 = 1;
+const REGISTERED: u8 = 2;
+const UNREGISTERED: u8 = 3;
+const REJECTED: u8 = 4;
+
 impl<'a> MetricDesc<'a> {
+    /// Describe a metric, panicking if its name, unit or label names are
+    /// invalid as per [MetricDesc::try_new]. When declared as a static,
+    /// an invalid descriptor therefore fails to compile:
+    ///
+    /// ```compile_fail
+    /// use discreet_metrics::{metrics::counter::Counter, MetricDesc};
+    ///
+    /// static METRIC: Counter = Counter::new();
+    /// static METRIC_DESC: MetricDesc =
+    ///     MetricDesc::new("some-metric", "Some metric", None, &[], &METRIC);
+    /// ```
     pub const fn new(
         name: &'a str,
         help: &'a str,
@@ -54,51 +371,499 @@ impl<'a> MetricDesc<'a> {
         labels: &'a [&'a str],
         metric: &'a (dyn Metric + Sync),
     ) -> Self {
-        Self {
+        match Self::try_new(name, help, unit, labels, metric) {
+            Ok(desc) => desc,
+            Err(e) => panic!("{}", e.as_str()),
+        }
+    }
+
+    /// Describe a metric, returning an error if its name, unit or label
+    /// names are invalid as per OpenMetrics. Names must not end with a
+    /// suffix that is reserved for samples, such as `_total`, and a
+    /// unit must be the suffix of the name e.g. `latency_seconds` with
+    /// `seconds`.
+    pub const fn try_new(
+        name: &'a str,
+        help: &'a str,
+        unit: Option<&'a str>,
+        labels: &'a [&'a str],
+        metric: &'a (dyn Metric + Sync),
+    ) -> Result<Self, Error> {
+        if !is_name(name.as_bytes(), true) {
+            return Err(Error::InvalidName);
+        }
+        let mut i = 0;
+        while i < RESERVED_SUFFIXES.len() {
+            if ends_with(name.as_bytes(), RESERVED_SUFFIXES[i].as_bytes()) {
+                return Err(Error::ReservedSuffix);
+            }
+            i += 1;
+        }
+        if let Some(unit) = unit {
+            let (name, unit) = (name.as_bytes(), unit.as_bytes());
+            if unit.is_empty()
+                || name.len() <= unit.len()
+                || name[name.len() - unit.len() - 1] != b'_'
+                || !ends_with(name, unit)
+            {
+                return Err(Error::InvalidUnit);
+            }
+        }
+        let mut i = 0;
+        while i < labels.len() {
+            if !is_label_name(labels[i].as_bytes()) {
+                return Err(Error::InvalidLabelName);
+            }
+            i += 1;
+        }
+        Ok(Self {
             name,
             help,
             unit,
             labels,
             metric,
             next: AtomicPtr::new(ptr::null_mut()),
+            state: AtomicU8::new(IDLE),
+            registry: AtomicPtr::new(ptr::null_mut()),
+            namespace: AtomicPtr::new(ptr::null_mut()),
+        })
+    }
+
+    /// The type of the metric being described.
+    pub fn metric_type(&self) -> MetricType {
+        self.metric.metric_type()
+    }
+
+    /// The metric being described, if it is of a given type e.g.
+    /// `desc.metric_as::<Counter>().map(Counter::total)`.
+    pub fn metric_as<M: Any>(&self) -> Option<&'a M> {
+        self.metric.as_any()?.downcast_ref()
+    }
+
+    /// The namespace that the descriptor was registered within, if any.
+    /// See [Namespace::register].
+    pub fn namespace(&self) -> Option<&'a Namespace<'a>> {
+        NonNull::new(self.namespace.load(Ordering::Acquire))
+            .map(|nonnull_namespace_ptr| unsafe { nonnull_namespace_ptr.as_ref() })
+    }
+
+    /// True if the descriptor is registered, and so is to be encoded
+    fn is_registered(&self) -> bool {
+        self.state.load(Ordering::Acquire) == REGISTERED
+    }
+
+    /// True if the descriptor was rejected when last registered
+    fn is_rejected(&self) -> bool {
+        self.state.load(Ordering::Acquire) == REJECTED
+    }
+
+    /// The prefix of the metric's name given its namespace, if any
+    fn prefix(&self) -> &'a str {
+        self.namespace().map_or("", |namespace| namespace.prefix)
+    }
+
+    /// True if the metric's name, prefixed by that of any namespace, is
+    /// also a valid label name.
+    pub(crate) fn is_label_name(&self) -> bool {
+        !self.prefix().contains(':') && !self.name.contains(':')
+    }
+
+    /// True if the metric is encoded with a given name, being its own
+    /// following any prefix of its namespace.
+    fn is_named(&self, prefix: &str, name: &str) -> bool {
+        self.prefix()
+            .bytes()
+            .chain(self.name.bytes())
+            .eq(prefix.bytes().chain(name.bytes()))
+    }
+}
+
+/// Suffixes given to the names of samples, and so reserved.
+const RESERVED_SUFFIXES: [&str; 8] = [
+    "_total", "_created", "_bucket", "_count", "_sum", "_gcount", "_gsum", "_info",
+];
+
+/// True for names of the form `[a-zA-Z_:][a-zA-Z0-9_:]*`, or without
+/// colons if not permitted.
+const fn is_name(name: &[u8], colons: bool) -> bool {
+    let mut i = 0;
+    while i < name.len() {
+        let valid = match name[i] {
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => true,
+            b'0'..=b'9' => i > 0,
+            b':' => colons,
+            _ => false,
+        };
+        if !valid {
+            return false;
         }
+        i += 1;
     }
+    !name.is_empty()
+}
+
+/// True for names of labels that are valid, and not reserved by
+/// beginning with `__`.
+const fn is_label_name(label: &[u8]) -> bool {
+    is_name(label, false) && !(label.len() > 1 && label[0] == b'_' && label[1] == b'_')
+}
+
+/// The names of labels applying to samples of any metric, which therefore
+/// may not be those that histograms and summaries label their samples with
+const fn check_label_names(labels: &[Label]) -> Result<(), Error> {
+    let mut i = 0;
+    while i < labels.len() {
+        let name = labels[i].name.as_bytes();
+        if !is_label_name(name) || eq(name, b"le") || eq(name, b"quantile") {
+            return Err(Error::InvalidLabelName);
+        }
+        let mut j = 0;
+        while j < i {
+            if eq(labels[i].name.as_bytes(), labels[j].name.as_bytes()) {
+                return Err(Error::DuplicateLabelName);
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+const fn eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && ends_with(a, b)
+}
+
+const fn ends_with(s: &[u8], suffix: &[u8]) -> bool {
+    if s.len() < suffix.len() {
+        return false;
+    }
+    let offset = s.len() - suffix.len();
+    let mut i = 0;
+    while i < suffix.len() {
+        if s[offset + i] != suffix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
 }
 
 /// A registry retains a collection of metrics.
 /// Metrics are retained in a chain of references
 /// that must live at least as long as the registry
 /// itself.
-#[derive(Default)]
+///
+/// A registry may also be given a section of metric descriptors
+/// that have been collected together at link time. These are
+/// encoded following those that have been registered at runtime.
+/// Those with the same name as another earlier in the section are
+/// rejected when first encoded, and so are never encoded.
+///
+/// A registry given a [Clock] provides metrics with their creation
+/// time when they are registered, or when first encoded if they are
+/// from a section.
+///
+/// A registry may also be given constant labels, such as those
+/// identifying a device, that are attached to every sample encoded.
+/// Those known only at runtime are copied into [ConstantLabels].
 pub struct Registry<'a> {
     head: AtomicPtr<MetricDesc<'a>>,
+    section: fn() -> &'a [&'a MetricDesc<'a>],
+    section_checked: AtomicBool,
+    clock: Option<&'a dyn Clock>,
+    labels_state: AtomicU8,
+    labels: UnsafeCell<&'a [Label<'a>]>,
+    label_storage: Option<&'a dyn LabelStorage<'a>>,
 }
 
+const LABELS_UNSET: u8 = 0;
+const LABELS_SETTING: u8 = 1;
+const LABELS_SET: u8 = 2;
+
+// Safety: the constant labels are written only by the thread that claims
+// them being unset, and are read only once they have been published as set.
+unsafe impl Sync for Registry<'_> {}
+
 impl<'a> Registry<'a> {
     pub const fn new() -> Self {
+        Self::with_section(|| &[])
+    }
+
+    /// Create a registry that also encodes the descriptors of a section,
+    /// such as those collected by the linker into `METRICS`.
+    pub const fn with_section(section: fn() -> &'a [&'a MetricDesc<'a>]) -> Self {
         Self {
             head: AtomicPtr::new(ptr::null_mut()),
+            section,
+            section_checked: AtomicBool::new(false),
+            clock: None,
+            labels_state: AtomicU8::new(LABELS_UNSET),
+            labels: UnsafeCell::new(&[]),
+            label_storage: None,
+        }
+    }
+
+    /// Provide the registry with a clock e.g.
+    /// `Registry::new().with_clock(&SystemClock)`.
+    pub const fn with_clock(self, clock: &'a dyn Clock) -> Self {
+        Self {
+            clock: Some(clock),
+            ..self
+        }
+    }
+
+    /// Provide the registry with storage for constant labels that are
+    /// known only at runtime e.g.
+    /// `Registry::new().with_label_storage(&LABELS)` given
+    /// `static LABELS: ConstantLabels<2, 64> = ConstantLabels::new()`.
+    /// See [Registry::set_labels].
+    pub const fn with_label_storage<const N: usize, const B: usize>(
+        self,
+        storage: &'a ConstantLabels<'a, N, B>,
+    ) -> Self {
+        Self {
+            label_storage: Some(storage),
+            ..self
+        }
+    }
+
+    /// Provide the registry with constant labels that are known at
+    /// compile time, panicking if any of their names are invalid or the
+    /// same. See [Registry::set_labels].
+    pub const fn with_labels(self, labels: &'a [Label<'a>]) -> Self {
+        if let Err(e) = check_label_names(labels) {
+            panic!("{}", e.as_str());
+        }
+        Self {
+            labels_state: AtomicU8::new(LABELS_SET),
+            labels: UnsafeCell::new(labels),
+            ..self
+        }
+    }
+
+    /// The current time given the registry's clock, if it has one
+    pub fn now(&self) -> Option<u64> {
+        self.clock.map(|clock| clock.now())
+    }
+
+    /// Set the constant labels of the registry, such as at startup once a
+    /// device's identity is known. The labels are copied into the
+    /// registry's [ConstantLabels], and so may be borrowed from anywhere.
+    /// They are attached to every sample encoded, preceding any others,
+    /// and so must not share their names. Constant labels may only be set
+    /// once, and should be set before metrics are registered concurrently.
+    pub fn set_labels(&self, labels: &[Label]) -> Result<(), Error> {
+        check_label_names(labels)?;
+        let Some(storage) = self.label_storage else {
+            return Err(Error::NoLabelStorage);
+        };
+        if self.labels_state.load(Ordering::Acquire) != LABELS_UNSET {
+            return Err(Error::LabelsAlreadySet);
+        }
+        for desc in self.iter() {
+            Self::check_label_collisions(labels, desc)?;
+        }
+        if self
+            .labels_state
+            .compare_exchange(
+                LABELS_UNSET,
+                LABELS_SETTING,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return Err(Error::LabelsAlreadySet);
+        }
+        match storage.store(labels) {
+            Ok(labels) => {
+                unsafe { *self.labels.get() = labels };
+                self.labels_state.store(LABELS_SET, Ordering::Release);
+                Ok(())
+            }
+            Err(e) => {
+                self.labels_state.store(LABELS_UNSET, Ordering::Release);
+                Err(e)
+            }
+        }
+    }
+
+    /// The constant labels of the registry, if they have been set
+    pub fn labels(&self) -> &'a [Label<'a>] {
+        if self.labels_state.load(Ordering::Acquire) == LABELS_SET {
+            unsafe { *self.labels.get() }
+        } else {
+            &[]
         }
     }
 
     /// Register a metric descriptor. Registration is synchronized
     /// and so may therefore be called from multiple threads.
-    pub fn register(&self, desc: &'a mut MetricDesc<'a>) {
-        let desc_ptr = desc as *mut _;
+    ///
+    /// A descriptor is rejected if it is registered already, or if
+    /// another descriptor of the registry or its section has the same
+    /// name. Of two descriptors with the same name being registered
+    /// concurrently, at most one is accepted. A rejected descriptor may
+    /// be registered again e.g. once the other has been unregistered.
+    /// The descriptors of the registry's section are encoded without being
+    /// registered, and so are rejected as registered already.
+    pub fn register(&self, desc: &'a MetricDesc<'a>) -> Result<(), Error> {
+        self.register_in(desc, None)
+    }
+
+    fn register_in(
+        &self,
+        desc: &'a MetricDesc<'a>,
+        namespace: Option<&'a Namespace<'a>>,
+    ) -> Result<(), Error> {
+        // The descriptors of the section are encoded without registering
+        if (self.section)().iter().any(|other| ptr::eq(desc, *other)) {
+            return Err(Error::AlreadyRegistered);
+        }
+        let state = desc.state.load(Ordering::Relaxed);
+        if matches!(state, CLAIMED | REGISTERED)
+            || desc
+                .state
+                .compare_exchange(state, CLAIMED, Ordering::SeqCst, Ordering::Relaxed)
+                .is_err()
+        {
+            return Err(Error::AlreadyRegistered);
+        }
+
+        let registry_ptr = self as *const _ as *mut _;
+        let linked = match desc.registry.compare_exchange(
+            ptr::null_mut(),
+            registry_ptr,
+            Ordering::Acquire,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => false,
+            Err(other_registry_ptr) if other_registry_ptr == registry_ptr => true,
+            Err(_) => {
+                desc.state.store(REJECTED, Ordering::Release);
+                return Err(Error::RegisteredElsewhere);
+            }
+        };
+        desc.namespace.store(
+            namespace.map_or(ptr::null_mut(), |namespace| namespace as *const _ as *mut _),
+            Ordering::Release,
+        );
+        if !linked {
+            self.link(desc);
+        }
+
+        // Having claimed the descriptor before checking, of two with the
+        // same name being registered concurrently, at least one sees the
+        // other.
+        let checked = Self::check_state_set_name(desc)
+            .and_then(|()| Self::check_label_collisions(self.labels(), desc))
+            .and_then(|()| {
+                (self.section)()
+                    .iter()
+                    .try_for_each(|other| Self::check(desc, other))
+            })
+            .and_then(|()| self.check_chain(desc));
+        if let Err(e) = checked {
+            desc.state.store(REJECTED, Ordering::Release);
+            return Err(e);
+        }
+
+        if let Some(now) = self.now() {
+            desc.metric.stamp(now);
+        }
+        desc.state.store(REGISTERED, Ordering::Release);
+        Ok(())
+    }
+
+    fn check_chain(&self, desc: &'a MetricDesc<'a>) -> Result<(), Error> {
+        let mut next = &self.head;
+        while let Some(other) = Self::load(next) {
+            if !ptr::eq(desc, other)
+                && matches!(other.state.load(Ordering::SeqCst), CLAIMED | REGISTERED)
+            {
+                Self::check(desc, other)?;
+            }
+            next = &other.next;
+        }
+        Ok(())
+    }
+
+    /// A state set's states are labelled by its metric's name, prefixed
+    /// by that of any namespace, and so must be a valid label name.
+    fn check_state_set_name(desc: &MetricDesc) -> Result<(), Error> {
+        if desc.metric_type() == MetricType::StateSet && !desc.is_label_name() {
+            Err(Error::InvalidLabelName)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The names of the labels of a descriptor's samples must differ, be
+    /// they constant labels, those of its namespace, its own, or those of
+    /// its metric. The states of a state set are also labelled by its name.
+    fn check_label_collisions(constant: &[Label], desc: &MetricDesc) -> Result<(), Error> {
+        let namespace = desc.namespace().map(Namespace::labels).unwrap_or_default();
+        let names = constant
+            .iter()
+            .chain(namespace)
+            .map(|label| label.name)
+            .chain(desc.labels.iter().copied());
+        for (i, name) in names.clone().enumerate() {
+            if names.clone().skip(i + 1).any(|other| other == name)
+                || desc.metric.has_label(name)
+                || (desc.metric_type() == MetricType::StateSet && desc.is_named("", name))
+            {
+                return Err(Error::DuplicateLabelName);
+            }
+        }
+        Ok(())
+    }
+
+    fn check(desc: &MetricDesc, other: &MetricDesc) -> Result<(), Error> {
+        if !desc.is_named(other.prefix(), other.name) {
+            Ok(())
+        } else if desc.metric_type() != other.metric_type() || desc.unit != other.unit {
+            Err(Error::ConflictingFamily)
+        } else {
+            Err(Error::DuplicateName)
+        }
+    }
 
+    /// Reject those descriptors of the section having the same name as
+    /// another earlier in the section, being state sets with colons in
+    /// their names, or having the same label names as the constant labels.
+    /// The section is fixed at link time, and so is checked just once,
+    /// before it is first encoded.
+    fn check_section(&self) {
+        if self.section_checked.load(Ordering::Acquire) {
+            return;
+        }
+        let section = (self.section)();
+        for (i, desc) in section.iter().enumerate() {
+            if Self::check_state_set_name(desc).is_err()
+                || Self::check_label_collisions(self.labels(), desc).is_err()
+                || section[..i]
+                    .iter()
+                    .any(|other| Self::check(desc, other).is_err())
+            {
+                desc.state.store(REJECTED, Ordering::Release);
+            }
+        }
+        self.section_checked.store(true, Ordering::Release);
+    }
+
+    /// Link a descriptor in at the head of the chain.
+    fn link(&self, desc: &'a MetricDesc<'a>) {
+        let desc_ptr = desc as *const _ as *mut _;
         loop {
             let head_desc_ptr = self.head.load(Ordering::Relaxed);
-            let prev_desc_ptr = desc.next.swap(head_desc_ptr, Ordering::Relaxed);
-            assert!(
-                head_desc_ptr != desc_ptr && prev_desc_ptr.is_null(),
-                "Metric is registered more than once"
-            );
+            desc.next.store(head_desc_ptr, Ordering::Relaxed);
             if self
                 .head
                 .compare_exchange(
                     head_desc_ptr,
                     desc_ptr,
-                    Ordering::Relaxed,
+                    Ordering::Release,
                     Ordering::Relaxed,
                 )
                 .is_ok()
@@ -107,19 +872,627 @@ impl<'a> Registry<'a> {
             }
         }
     }
+
+    /// Unregister a metric descriptor so that it is no longer encoded,
+    /// returning false if it was not registered. The descriptor may be
+    /// registered again later e.g. when a connection that it describes is
+    /// reopened. Descriptors of a section cannot be unregistered.
+    ///
+    /// An unregistered descriptor is hidden rather than unlinked: it
+    /// remains linked into the registry, and retains its place there
+    /// should it be registered again. Unlinking would require waiting
+    /// for any encoding in progress to move past the descriptor, which an
+    /// interrupt that preempted the encoding could wait for forever.
+    /// Encoding may instead proceed concurrently, and will encode the
+    /// descriptor or not, but never anything else in its place, nor
+    /// anything twice. A descriptor must therefore outlive the registry
+    /// regardless of it being unregistered:
+    ///
+    /// ```compile_fail
+    /// use discreet_metrics::{
+    ///     encoders::{text::TextEncoder, SliceSink},
+    ///     metrics::counter::Counter,
+    ///     MetricDesc, Registry,
+    /// };
+    ///
+    /// let registry = Registry::new();
+    /// {
+    ///     let metric = Counter::new();
+    ///     let desc = MetricDesc::new("connection", "A connection", None, &[], &metric);
+    ///     registry.register(&desc).unwrap();
+    ///     registry.unregister(&desc);
+    /// }
+    /// let mut buf = [0; 64];
+    /// registry.encode(&mut TextEncoder::new(SliceSink::new(&mut buf))).unwrap();
+    /// ```
+    ///
+    /// A descriptor, once registered with a registry, cannot be registered
+    /// with another.
+    pub fn unregister(&self, desc: &'a MetricDesc<'a>) -> bool {
+        ptr::eq(desc.registry.load(Ordering::Acquire), self)
+            && desc
+                .state
+                .compare_exchange(
+                    REGISTERED,
+                    UNREGISTERED,
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+    }
+}
+
+/// Fixed storage for the constant labels of a registry, having room for
+/// `N` labels and `B` bytes of their names and values. Labels known only
+/// at runtime, such as a device's serial number read from flash, are
+/// copied in so that they need not be static. See
+/// [Registry::with_label_storage].
+pub struct ConstantLabels<'a, const N: usize, const B: usize> {
+    claimed: AtomicBool,
+    labels: UnsafeCell<[Label<'a>; N]>,
+    bytes: UnsafeCell<[u8; B]>,
+}
+
+// Safety: the labels are written only by the thread that claims the
+// storage, and are read only once published as set by a registry.
+unsafe impl<const N: usize, const B: usize> Sync for ConstantLabels<'_, N, B> {}
+
+impl<const N: usize, const B: usize> ConstantLabels<'_, N, B> {
+    pub const fn new() -> Self {
+        Self {
+            claimed: AtomicBool::new(false),
+            labels: UnsafeCell::new([const { Label::new("", LabelValue::Unsigned(0)) }; N]),
+            bytes: UnsafeCell::new([0; B]),
+        }
+    }
+}
+
+impl<const N: usize, const B: usize> Default for ConstantLabels<'_, N, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Storage that constant labels are copied into, once only
+trait LabelStorage<'a>: Sync {
+    fn store(&'a self, labels: &[Label]) -> Result<&'a [Label<'a>], Error>;
+}
+
+impl<'a, const N: usize, const B: usize> LabelStorage<'a> for ConstantLabels<'a, N, B> {
+    fn store(&'a self, labels: &[Label]) -> Result<&'a [Label<'a>], Error> {
+        let len = |label: &Label| {
+            label.name.len()
+                + match label.value {
+                    LabelValue::Str(s) => s.len(),
+                    LabelValue::Hex(bytes) => bytes.len(),
+                    _ => 0,
+                }
+        };
+        if labels.len() > N || labels.iter().map(len).sum::<usize>() > B {
+            return Err(Error::LabelsTooLarge);
+        }
+        if self.claimed.swap(true, Ordering::Acquire) {
+            return Err(Error::LabelsAlreadySet);
+        }
+
+        fn copy<'b>(bytes: &mut &'b mut [u8], from: &[u8]) -> &'b [u8] {
+            let (to, rest) = core::mem::take(bytes).split_at_mut(from.len());
+            to.copy_from_slice(from);
+            *bytes = rest;
+            to
+        }
+        fn copy_str<'b>(bytes: &mut &'b mut [u8], from: &str) -> &'b str {
+            // Safety: the bytes are copied from a str
+            unsafe { core::str::from_utf8_unchecked(copy(bytes, from.as_bytes())) }
+        }
+
+        // Safety: the storage has been claimed
+        let (stored, bytes) = unsafe { (&mut *self.labels.get(), &mut *self.bytes.get()) };
+        let mut bytes = &mut bytes[..];
+        for (to, from) in stored.iter_mut().zip(labels) {
+            let value = match from.value {
+                LabelValue::Str(s) => LabelValue::Str(copy_str(&mut bytes, s)),
+                LabelValue::Hex(h) => LabelValue::Hex(copy(&mut bytes, h)),
+                LabelValue::Unsigned(v) => LabelValue::Unsigned(v),
+                LabelValue::Signed(v) => LabelValue::Signed(v),
+                LabelValue::Decimal { value, places } => LabelValue::Decimal { value, places },
+            };
+            *to = Label::new(copy_str(&mut bytes, from.name), value);
+        }
+        Ok(&stored[..labels.len()])
+    }
+}
+
+impl Default for Registry<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "linkme")]
+impl Registry<'static> {
+    /// Create a registry that also encodes the metrics declared
+    /// anywhere within the binary using [metric!] with `section: METRICS`.
+    pub const fn linked() -> Self {
+        Self::with_section(|| &METRICS)
+    }
 }
 
 impl<'a> Registry<'a> {
-    /// Collect the registered metrics and encode them
-    pub fn encode(&self, enc: &mut dyn Encoder) {
-        let mut next = &self.head;
-        while let Some(nonnull_desc_ptr) = NonNull::new(next.load(Ordering::Relaxed)) {
-            let desc = unsafe { nonnull_desc_ptr.as_ref() };
-            enc.write_desc(desc);
-            desc.metric.encode(enc);
-            next = &desc.next;
+    /// Iterate over the registered descriptors, followed by those of
+    /// the registry's section, in the order that they are encoded.
+    /// Registration may proceed concurrently, as with encoding.
+    pub fn iter(&self) -> Iter<'a> {
+        self.check_section();
+        Iter {
+            next: Self::load(&self.head),
+            section: (self.section)().iter(),
         }
     }
+
+    /// Find a descriptor by its metric's name, including the prefix of
+    /// any namespace, such as to inspect the metric's value e.g.
+    /// `registry.get("requests")?.metric_as::<Counter>()?.total()`.
+    pub fn get(&self, name: &str) -> Option<&'a MetricDesc<'a>> {
+        self.iter().find(|desc| desc.is_named("", name))
+    }
+
+    /// Collect the registered metrics and encode them. Encoding stops
+    /// at the first error returned by the encoder.
+    pub fn encode(&self, enc: &mut dyn Encoder<'a>) -> Result<(), Error> {
+        Self::encode_all(&[self], None, enc)
+    }
+
+    /// Collect the registered metrics selected by a filter and encode
+    /// them e.g. `registry.encode_filtered(&Filter::Prefix("net_"), enc)`.
+    /// Those not selected are omitted entirely, and are not stamped with
+    /// their creation time. See [Registry::encode].
+    pub fn encode_filtered(&self, filter: &Filter, enc: &mut dyn Encoder<'a>) -> Result<(), Error> {
+        Self::encode_all(&[self], Some(filter), enc)
+    }
+
+    /// Collect the registered metrics and encode them, starting from
+    /// where a previous call left off. Only whole metrics are written,
+    /// so that when the encoder's output fills, the metric being
+    /// written is discarded and the cursor left pointing at it. The
+    /// caller may then drain the output and call again with the same
+    /// cursor until [Cursor::is_done] returns true. The concatenation
+    /// of each call's output is the same as that of a single call to
+    /// [Registry::encode].
+    ///
+    /// An error is returned if nothing at all could be written, such as
+    /// when a single metric requires more room than the output has.
+    pub fn encode_from(
+        &self,
+        cursor: &mut Cursor<'a>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        Self::encode_all_from(&[self], None, cursor, enc)
+    }
+
+    /// Collect the registered metrics selected by a filter and encode
+    /// them, starting from where a previous call left off. See
+    /// [Registry::encode_filtered] and [Registry::encode_from].
+    pub fn encode_filtered_from(
+        &self,
+        filter: &Filter,
+        cursor: &mut Cursor<'a>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        Self::encode_all_from(&[self], Some(filter), cursor, enc)
+    }
+
+    /// Encode the metrics of several registries as one, omitting any
+    /// metric with the same name as one of an earlier registry, or not
+    /// selected by a filter.
+    fn encode_all(
+        registries: &[&Self],
+        filter: Option<&Filter>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        for (i, registry) in registries.iter().enumerate() {
+            let now = registry.now();
+            enc.set_constant_labels(registry.labels());
+            for desc in registry.iter() {
+                if Self::is_selected(filter, desc) && !Self::is_shadowed(&registries[..i], desc) {
+                    Self::encode_desc(desc, now, enc)?;
+                }
+            }
+        }
+        enc.write_eof()
+    }
+
+    fn encode_all_from(
+        registries: &[&Self],
+        filter: Option<&Filter>,
+        cursor: &mut Cursor<'a>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        let mut now = None;
+        let mut current = None;
+        let mut progressed = false;
+        loop {
+            if current != Some(cursor.registry) {
+                if let Some(registry) = registries.get(cursor.registry) {
+                    registry.check_section();
+                    now = registry.now();
+                    enc.set_constant_labels(registry.labels());
+                }
+                current = Some(cursor.registry);
+            }
+            let earlier = &registries[..cursor.registry.min(registries.len())];
+            let position = enc.position();
+            let result = match cursor.position {
+                CursorPosition::Start => {
+                    cursor.position = match registries.get(cursor.registry) {
+                        Some(registry) => CursorPosition::after(&registry.head),
+                        None => CursorPosition::Eof,
+                    };
+                    continue;
+                }
+                CursorPosition::Desc(desc)
+                    if !desc.is_registered()
+                        || !Self::is_selected(filter, desc)
+                        || Self::is_shadowed(earlier, desc) =>
+                {
+                    Ok(())
+                }
+                CursorPosition::Desc(desc) => Self::encode_desc(desc, now, enc),
+                CursorPosition::Section(i) => {
+                    match (registries[cursor.registry].section)().get(i) {
+                        Some(desc)
+                            if desc.is_rejected()
+                                || !Self::is_selected(filter, desc)
+                                || Self::is_shadowed(earlier, desc) =>
+                        {
+                            Ok(())
+                        }
+                        Some(desc) => Self::encode_desc(desc, now, enc),
+                        None if cursor.registry + 1 < registries.len() => {
+                            cursor.registry += 1;
+                            cursor.position = CursorPosition::Start;
+                            continue;
+                        }
+                        None => {
+                            cursor.position = CursorPosition::Eof;
+                            continue;
+                        }
+                    }
+                }
+                CursorPosition::Eof => enc.write_eof(),
+                CursorPosition::Done => return Ok(()),
+            };
+            match result {
+                Ok(()) => {
+                    cursor.position = match cursor.position {
+                        CursorPosition::Desc(desc) => CursorPosition::after(&desc.next),
+                        CursorPosition::Section(i) => CursorPosition::Section(i + 1),
+                        _ => CursorPosition::Done,
+                    };
+                    progressed = true;
+                }
+                Err(Error::BufferFull) if progressed => {
+                    enc.rewind(position);
+                    return Ok(());
+                }
+                Err(e) => {
+                    enc.rewind(position);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// True if a descriptor is selected by a filter, if any
+    fn is_selected(filter: Option<&Filter>, desc: &MetricDesc) -> bool {
+        filter.is_none_or(|filter| filter.matches(desc))
+    }
+
+    /// True if a descriptor has the same name as one of other registries
+    fn is_shadowed(registries: &[&Self], desc: &MetricDesc) -> bool {
+        registries.iter().any(|registry| {
+            registry
+                .iter()
+                .any(|other| other.is_named(desc.prefix(), desc.name))
+        })
+    }
+
+    fn load(next: &AtomicPtr<MetricDesc<'a>>) -> Option<&'a MetricDesc<'a>> {
+        NonNull::new(next.load(Ordering::Acquire))
+            .map(|nonnull_desc_ptr| unsafe { nonnull_desc_ptr.as_ref() })
+    }
+
+    fn encode_desc(
+        desc: &'a MetricDesc<'a>,
+        now: Option<u64>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        if let Some(now) = now {
+            desc.metric.stamp(now);
+        }
+        enc.write_desc(desc)?;
+        desc.metric.encode(enc)
+    }
+}
+
+/// Several registries encoded as one, such as those of libraries along
+/// with that of an application. The metrics of each registry are encoded
+/// in turn, followed by the end of them all:
+///
+/// ```
+/// use discreet_metrics::{
+///     encoders::{text::TextEncoder, SliceSink},
+///     CompositeRegistry, Registry,
+/// };
+///
+/// static LIBRARY_REGISTRY: Registry = Registry::new();
+/// static APP_REGISTRY: Registry = Registry::new();
+/// static REGISTRY: CompositeRegistry =
+///     CompositeRegistry::new(&[&APP_REGISTRY, &LIBRARY_REGISTRY]);
+///
+/// let mut buf = [0; 1024];
+/// let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+/// REGISTRY.encode(&mut encoder).unwrap();
+/// assert_eq!(encoder.into_inner().written(), b"# EOF\n");
+/// ```
+///
+/// Registries are independent of one another, and so may each have a
+/// metric of the same name. Only the first registry's is encoded, so that
+/// names remain unique.
+pub struct CompositeRegistry<'a> {
+    registries: &'a [&'a Registry<'a>],
+}
+
+impl<'a> CompositeRegistry<'a> {
+    pub const fn new(registries: &'a [&'a Registry<'a>]) -> Self {
+        Self { registries }
+    }
+
+    /// The registries, in the order that they are encoded
+    pub fn registries(&self) -> &'a [&'a Registry<'a>] {
+        self.registries
+    }
+
+    /// Find a descriptor by its metric's name within the first registry
+    /// having it. See [Registry::get].
+    pub fn get(&self, name: &str) -> Option<&'a MetricDesc<'a>> {
+        self.registries
+            .iter()
+            .find_map(|registry| registry.get(name))
+    }
+
+    /// Collect the metrics of each registry and encode them. See
+    /// [Registry::encode].
+    pub fn encode(&self, enc: &mut dyn Encoder<'a>) -> Result<(), Error> {
+        Registry::encode_all(self.registries, None, enc)
+    }
+
+    /// Collect the metrics of each registry selected by a filter and
+    /// encode them. See [Registry::encode_filtered].
+    pub fn encode_filtered(&self, filter: &Filter, enc: &mut dyn Encoder<'a>) -> Result<(), Error> {
+        Registry::encode_all(self.registries, Some(filter), enc)
+    }
+
+    /// Collect the metrics of each registry and encode them, starting
+    /// from where a previous call left off. See [Registry::encode_from].
+    pub fn encode_from(
+        &self,
+        cursor: &mut Cursor<'a>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        Registry::encode_all_from(self.registries, None, cursor, enc)
+    }
+
+    /// Collect the metrics of each registry selected by a filter and
+    /// encode them, starting from where a previous call left off. See
+    /// [Registry::encode_filtered_from].
+    pub fn encode_filtered_from(
+        &self,
+        filter: &Filter,
+        cursor: &mut Cursor<'a>,
+        enc: &mut dyn Encoder<'a>,
+    ) -> Result<(), Error> {
+        Registry::encode_all_from(self.registries, Some(filter), cursor, enc)
+    }
+}
+
+/// A view of a registry that prefixes the names of the metrics registered
+/// through it, and attaches constant labels to their samples, when they
+/// are encoded. Subsystems sharing a registry may so name their metrics
+/// consistently:
+///
+/// ```
+/// use discreet_metrics::{
+///     encoders::{text::TextEncoder, SliceSink},
+///     metrics::counter::Counter,
+///     Label, LabelValue, MetricDesc, Namespace, Registry,
+/// };
+///
+/// static REGISTRY: Registry = Registry::new();
+/// static NET: Namespace = Namespace::new(&REGISTRY, "net_")
+///     .with_labels(&[Label::new("interface", LabelValue::Str("eth0"))]);
+///
+/// static PACKETS: Counter = Counter::new();
+/// static PACKETS_DESC: MetricDesc =
+///     MetricDesc::new("packets", "Packets received", None, &[], &PACKETS);
+///
+/// NET.register(&PACKETS_DESC).unwrap();
+///
+/// let mut buf = [0; 128];
+/// let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+/// REGISTRY.encode(&mut encoder).unwrap();
+/// assert!(core::str::from_utf8(encoder.into_inner().written())
+///     .unwrap()
+///     .contains("net_packets_total{interface=\"eth0\"} 0\n"));
+/// ```
+pub struct Namespace<'a> {
+    registry: &'a Registry<'a>,
+    prefix: &'a str,
+    labels: &'a [Label<'a>],
+}
+
+impl<'a> Namespace<'a> {
+    /// Create a namespace of a registry given the prefix of its metrics'
+    /// names, panicking if the prefix could not begin a valid name.
+    pub const fn new(registry: &'a Registry<'a>, prefix: &'a str) -> Self {
+        if !is_name(prefix.as_bytes(), true) {
+            panic!("{}", Error::InvalidName.as_str());
+        }
+        Self {
+            registry,
+            prefix,
+            labels: &[],
+        }
+    }
+
+    /// Attach constant labels to the samples of the namespace's metrics,
+    /// panicking if any of their names are invalid.
+    pub const fn with_labels(self, labels: &'a [Label<'a>]) -> Self {
+        if let Err(e) = check_label_names(labels) {
+            panic!("{}", e.as_str());
+        }
+        Self { labels, ..self }
+    }
+
+    /// The prefix of the namespace's metrics' names
+    pub fn prefix(&self) -> &'a str {
+        self.prefix
+    }
+
+    /// The constant labels of the namespace's metrics' samples
+    pub fn labels(&self) -> &'a [Label<'a>] {
+        self.labels
+    }
+
+    /// Register a metric descriptor with the registry, within this
+    /// namespace. A descriptor is rejected if another has the same name
+    /// once prefixed. See [Registry::register].
+    pub fn register(&'a self, desc: &'a MetricDesc<'a>) -> Result<(), Error> {
+        self.registry.register_in(desc, Some(self))
+    }
+
+    /// Unregister a metric descriptor. See [Registry::unregister].
+    pub fn unregister(&self, desc: &'a MetricDesc<'a>) -> bool {
+        self.registry.unregister(desc)
+    }
+}
+
+/// A metric along with its descriptor that registers itself with a
+/// registry when first used. Registered metrics are typically declared
+/// using the [metric!] macro.
+///
+/// Only first use registers the metric. Once unregistered or rejected,
+/// the metric remains so regardless of its use until registered again
+/// explicitly with [Registered::register].
+pub struct Registered<'a, M> {
+    metric: &'a M,
+    desc: &'a MetricDesc<'a>,
+    registry: &'a Registry<'a>,
+}
+
+impl<'a, M> Registered<'a, M> {
+    pub const fn new(metric: &'a M, desc: &'a MetricDesc<'a>, registry: &'a Registry<'a>) -> Self {
+        Self {
+            metric,
+            desc,
+            registry,
+        }
+    }
+
+    /// Register the metric now rather than when first used, so as to learn
+    /// of it being rejected, or to register it again once unregistered or
+    /// rejected. A rejected metric may still be used, but is not encoded,
+    /// and is not registered again on use. Registering more than once has
+    /// no effect.
+    pub fn register(&self) -> Result<(), Error> {
+        if self.desc.is_registered() {
+            return Ok(());
+        }
+        match self.registry.register(self.desc) {
+            Err(Error::AlreadyRegistered) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// The descriptor of the metric
+    pub fn desc(&self) -> &'a MetricDesc<'a> {
+        self.desc
+    }
+}
+
+impl<M> Deref for Registered<'_, M> {
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        if self.desc.state.load(Ordering::Relaxed) == IDLE {
+            let _ = self.register();
+        }
+        self.metric
+    }
+}
+
+/// An iterator over the descriptors of a registry. See [Registry::iter].
+pub struct Iter<'a> {
+    next: Option<&'a MetricDesc<'a>>,
+    section: core::slice::Iter<'a, &'a MetricDesc<'a>>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a MetricDesc<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(desc) = self.next {
+            self.next = Registry::load(&desc.next);
+            if desc.is_registered() {
+                return Some(desc);
+            }
+        }
+        self.section
+            .by_ref()
+            .copied()
+            .find(|desc| !desc.is_rejected())
+    }
+}
+
+/// Records where encoding of a registry is to resume from.
+/// See [Registry::encode_from].
+#[derive(Default)]
+pub struct Cursor<'a> {
+    registry: usize,
+    position: CursorPosition<'a>,
+}
+
+#[derive(Clone, Copy, Default)]
+enum CursorPosition<'a> {
+    #[default]
+    Start,
+    Desc(&'a MetricDesc<'a>),
+    Section(usize),
+    Eof,
+    Done,
+}
+
+impl<'a> CursorPosition<'a> {
+    fn after(next: &AtomicPtr<MetricDesc<'a>>) -> Self {
+        match Registry::load(next) {
+            Some(desc) => CursorPosition::Desc(desc),
+            None => CursorPosition::Section(0),
+        }
+    }
+}
+
+impl Cursor<'_> {
+    pub const fn new() -> Self {
+        Self {
+            registry: 0,
+            position: CursorPosition::Start,
+        }
+    }
+
+    /// True when all metrics and the end of them have been encoded
+    pub fn is_done(&self) -> bool {
+        matches!(self.position, CursorPosition::Done)
+    }
 }
 
 #[cfg(test)]
@@ -149,26 +1522,44 @@ mod tests {
             }
         }
         impl Metric for MyMetric {
-            fn encode(&self, enc: &mut dyn Encoder) {
-                enc.write(&self.count.load(Ordering::Relaxed).to_string().as_bytes());
+            fn metric_type(&self) -> MetricType {
+                MetricType::Counter
+            }
+
+            fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+                enc.write(self.count.load(Ordering::Relaxed).to_string().as_bytes())
             }
         }
 
         struct MyEncoder;
-        impl Encoder for MyEncoder {
-            fn write_desc(&mut self, desc: &MetricDesc)
-            where
-                Self: Sized,
-            {
-                assert_eq!(desc.name, "some-metric");
+        impl Encoder<'_> for MyEncoder {
+            fn write_desc(&mut self, desc: &MetricDesc) -> Result<(), Error> {
+                assert_eq!(desc.name, "some_metric");
                 assert_eq!(desc.help, "Some metric");
                 assert!(desc.unit.is_none());
-                assert_eq!(desc.labels, ["some-label"]);
+                assert_eq!(desc.labels, ["some_label"]);
+                assert_eq!(desc.metric_type(), MetricType::Counter);
+                Ok(())
             }
 
-            fn write(&mut self, bytes: &[u8]) {
+            fn write_sample(&mut self, _sample: &Sample) -> Result<(), Error> {
+                unreachable!()
+            }
+
+            fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
                 assert_eq!(bytes, b"1");
+                Ok(())
+            }
+
+            fn write_eof(&mut self) -> Result<(), Error> {
+                Ok(())
             }
+
+            fn position(&self) -> usize {
+                0
+            }
+
+            fn rewind(&mut self, _position: usize) {}
         }
 
         // A registry will be typically declared in a static
@@ -177,12 +1568,12 @@ mod tests {
         // The user will declare a metric in their file, again as a static
         static METRIC: MyMetric = MyMetric::new();
 
-        // The above line and the following can probably be done as a macro
-        static mut METRIC_ITEM: MetricDesc =
-            MetricDesc::new("some-metric", "Some metric", None, &["some-label"], &METRIC);
+        // The above line and the following can also be done with the metric! macro
+        static METRIC_ITEM: MetricDesc =
+            MetricDesc::new("some_metric", "Some metric", None, &["some_label"], &METRIC);
 
-        // A metric desc can only be registered once and will panic otherwise!
-        REGISTRY.register(unsafe { &mut METRIC_ITEM });
+        // A metric desc can only be registered once and is rejected otherwise
+        REGISTRY.register(&METRIC_ITEM).unwrap();
 
         // This'll be what most people will have in the same file as the metric static
         METRIC.inc();
@@ -190,6 +1581,684 @@ mod tests {
         // From elsewhere, we'd be establishing the encoder and outputting
         // its bytes somewhere either periodically or on demand.
         let mut encoder = MyEncoder;
-        let _encoder = REGISTRY.encode(&mut encoder);
+        assert!(REGISTRY.encode(&mut encoder).is_ok());
+    }
+
+    #[test]
+    fn registration_more_than_once() {
+        static REGISTRY: Registry = Registry::new();
+        static METRIC: metrics::counter::Counter = metrics::counter::Counter::new();
+        static METRIC_ITEM: MetricDesc =
+            MetricDesc::new("some_metric", "Some metric", None, &[], &METRIC);
+
+        assert_eq!(REGISTRY.register(&METRIC_ITEM), Ok(()));
+        assert_eq!(
+            REGISTRY.register(&METRIC_ITEM),
+            Err(Error::AlreadyRegistered)
+        );
+
+        static OTHER_REGISTRY: Registry = Registry::new();
+        assert!(!OTHER_REGISTRY.unregister(&METRIC_ITEM));
+        assert!(REGISTRY.unregister(&METRIC_ITEM));
+        assert_eq!(
+            OTHER_REGISTRY.register(&METRIC_ITEM),
+            Err(Error::RegisteredElsewhere)
+        );
+        assert_eq!(REGISTRY.register(&METRIC_ITEM), Ok(()));
+    }
+
+    #[test]
+    fn registration_of_duplicate_names() {
+        use crate::metrics::{counter::Counter, gauge::Gauge};
+
+        static REGISTRY: Registry = Registry::new();
+        static COUNTERS: [Counter; 2] = [const { Counter::new() }; 2];
+        static GAUGE: Gauge = Gauge::new();
+        static FIRST: MetricDesc = MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[0]);
+        static SECOND: MetricDesc =
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[1]);
+        static THIRD: MetricDesc = MetricDesc::new("requests", "Requests", None, &[], &GAUGE);
+
+        REGISTRY.register(&FIRST).unwrap();
+        assert_eq!(REGISTRY.register(&SECOND), Err(Error::DuplicateName));
+        assert_eq!(REGISTRY.register(&THIRD), Err(Error::ConflictingFamily));
+
+        // Rejected descriptors may be registered once the name is free
+        assert!(REGISTRY.unregister(&FIRST));
+        REGISTRY.register(&THIRD).unwrap();
+    }
+
+    #[test]
+    fn registration_of_duplicate_names_in_a_section() {
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::{counter::Counter, gauge::Gauge},
+        };
+
+        static COUNTERS: [Counter; 2] = [const { Counter::new() }; 2];
+        static GAUGE: Gauge = Gauge::new();
+        static DESCS: [MetricDesc; 3] = [
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[0]),
+            MetricDesc::new("requests", "Requests again", None, &[], &COUNTERS[1]),
+            MetricDesc::new("requests", "Requests as a gauge", None, &[], &GAUGE),
+        ];
+        static SECTION: [&MetricDesc; 3] = [&DESCS[0], &DESCS[1], &DESCS[2]];
+        static REGISTRY: Registry = Registry::with_section(|| &SECTION);
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            "# TYPE requests counter\n\
+             # HELP requests Requests\n\
+             requests_total 0\n\
+             # EOF\n"
+        );
+        assert_eq!(
+            REGISTRY.iter().map(|desc| desc.help).collect::<Vec<_>>(),
+            ["Requests"]
+        );
+
+        // Registering a descriptor of the section leaves it as it was
+        assert_eq!(REGISTRY.register(&DESCS[0]), Err(Error::AlreadyRegistered));
+        assert_eq!(
+            REGISTRY.iter().map(|desc| desc.help).collect::<Vec<_>>(),
+            ["Requests"]
+        );
+    }
+
+    #[test]
+    fn registration_of_duplicate_names_concurrently() {
+        use crate::metrics::counter::Counter;
+
+        static REGISTRY: Registry = Registry::new();
+        static COUNTERS: [Counter; 4] = [const { Counter::new() }; 4];
+        static DESCS: [MetricDesc; 4] = [
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[0]),
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[1]),
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[2]),
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[3]),
+        ];
+
+        for _ in 0..1000 {
+            let barrier = std::sync::Barrier::new(DESCS.len());
+            let accepted: Vec<_> = std::thread::scope(|scope| {
+                let registering: Vec<_> = DESCS
+                    .iter()
+                    .map(|desc| {
+                        let barrier = &barrier;
+                        scope.spawn(move || {
+                            barrier.wait();
+                            REGISTRY.register(desc).is_ok().then_some(desc)
+                        })
+                    })
+                    .collect();
+                registering
+                    .into_iter()
+                    .filter_map(|registering| registering.join().unwrap())
+                    .collect()
+            });
+            assert!(accepted.len() <= 1);
+            for desc in accepted {
+                assert!(REGISTRY.unregister(desc));
+            }
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        use crate::metrics::{
+            counter::Counter,
+            family::{Family, OnFull},
+            gauge::Gauge,
+        };
+
+        static REGISTRY: Registry = Registry::new();
+        static REQUESTS: Counter = Counter::new();
+        static REQUESTS_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests", None, &[], &REQUESTS);
+        static TEMPERATURE: Gauge = Gauge::new();
+        static TEMPERATURE_DESC: MetricDesc =
+            MetricDesc::new("temperature", "Temperature", None, &[], &TEMPERATURE);
+
+        REGISTRY.register(&REQUESTS_DESC).unwrap();
+        REGISTRY.register(&TEMPERATURE_DESC).unwrap();
+        assert_eq!(
+            REGISTRY.iter().map(|desc| desc.name).collect::<Vec<_>>(),
+            ["temperature", "requests"]
+        );
+
+        REQUESTS.inc_by(2);
+        let desc = REGISTRY.get("requests").unwrap();
+        assert_eq!(desc.metric_as::<Counter>().map(Counter::total), Some(2));
+        assert!(desc.metric_as::<Gauge>().is_none());
+        assert!(REGISTRY.get("responses").is_none());
+
+        static RESPONSES: Family<u16, Counter, 2> =
+            Family::new([const { Counter::new() }; 2], OnFull::Reject);
+        static RESPONSES_DESC: MetricDesc =
+            MetricDesc::new("responses", "Responses", None, &["code"], &RESPONSES);
+        REGISTRY.register(&RESPONSES_DESC).unwrap();
+        RESPONSES.get_or_insert(200).unwrap().inc();
+        let responses = REGISTRY
+            .get("responses")
+            .and_then(|desc| desc.metric_as::<Family<u16, Counter, 2>>())
+            .unwrap();
+        assert_eq!(responses.get(200).map(Counter::total), Some(1));
+
+        assert!(REGISTRY.unregister(&REQUESTS_DESC));
+        assert!(REGISTRY.get("requests").is_none());
+    }
+
+    #[test]
+    fn namespaces() {
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::counter::Counter,
+        };
+
+        static REGISTRY: Registry = Registry::new();
+        static NET: Namespace = Namespace::new(&REGISTRY, "net_")
+            .with_labels(&[Label::new("interface", LabelValue::Str("eth0"))]);
+        static STORAGE: Namespace = Namespace::new(&REGISTRY, "storage_");
+
+        static COUNTERS: [Counter; 3] = [const { Counter::new() }; 3];
+        static NET_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests", None, &["kind"], &COUNTERS[0]);
+        static STORAGE_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[1]);
+        static PREFIXED_DESC: MetricDesc =
+            MetricDesc::new("net_requests", "Requests", None, &[], &COUNTERS[2]);
+
+        NET.register(&NET_DESC).unwrap();
+        STORAGE.register(&STORAGE_DESC).unwrap();
+        assert_eq!(REGISTRY.register(&PREFIXED_DESC), Err(Error::DuplicateName));
+
+        assert!(ptr::eq(REGISTRY.get("net_requests").unwrap(), &NET_DESC));
+        assert!(REGISTRY.get("requests").is_none());
+
+        let mut buf = [0; 512];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        encoder.write_desc(&NET_DESC).unwrap();
+        encoder
+            .write_sample(
+                &Sample::new("_total", Value::Unsigned(1))
+                    .with_label_values(&[LabelValue::Str("read")]),
+            )
+            .unwrap();
+        encoder.write_desc(&STORAGE_DESC).unwrap();
+        STORAGE_DESC.metric.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE net_requests counter
+# HELP net_requests Requests
+net_requests_total{interface="eth0",kind="read"} 1
+# TYPE storage_requests counter
+# HELP storage_requests Requests
+storage_requests_total 0
+"#
+        );
+    }
+
+    #[test]
+    fn constant_labels() {
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::counter::Counter,
+        };
+
+        let requests = Counter::new();
+        let requests_desc = MetricDesc::new("requests", "Requests", None, &[], &requests);
+        let responses = Counter::new();
+        let responses_desc = MetricDesc::new("responses", "Responses", None, &["site"], &responses);
+
+        let storage = ConstantLabels::<2, 24>::new();
+        let registry = Registry::new().with_label_storage(&storage);
+        {
+            // Labels known only at runtime are copied, and need not be static
+            let device_id = [0x0a, 0x1b];
+            let site = String::from("north");
+            let labels = [
+                Label::new("device_id", LabelValue::Hex(&device_id)),
+                Label::new("site", LabelValue::Str(&site)),
+            ];
+            let reserved = [Label::new("__site", LabelValue::Str(&site))];
+            let same = [labels[1], labels[1]];
+            let more = [
+                labels[0],
+                labels[1],
+                Label::new("a", LabelValue::Unsigned(1)),
+            ];
+            let larger = [Label::new(
+                "site",
+                LabelValue::Str("north by north west, beyond"),
+            )];
+
+            assert_eq!(registry.set_labels(&reserved), Err(Error::InvalidLabelName));
+            assert_eq!(registry.set_labels(&same), Err(Error::DuplicateLabelName));
+            assert_eq!(registry.set_labels(&more), Err(Error::LabelsTooLarge));
+            assert_eq!(registry.set_labels(&larger), Err(Error::LabelsTooLarge));
+
+            registry.register(&responses_desc).unwrap();
+            assert_eq!(registry.set_labels(&labels), Err(Error::DuplicateLabelName));
+            assert!(registry.unregister(&responses_desc));
+
+            registry.set_labels(&labels).unwrap();
+            assert_eq!(registry.set_labels(&labels), Err(Error::LabelsAlreadySet));
+        }
+        registry.register(&requests_desc).unwrap();
+        assert_eq!(
+            registry.register(&responses_desc),
+            Err(Error::DuplicateLabelName)
+        );
+
+        let site_labels = [Label::new("site", LabelValue::Str("south"))];
+        let site = Namespace::new(&registry, "site_").with_labels(&site_labels);
+        let sites = Counter::new();
+        let sites_desc = MetricDesc::new("sites", "Sites", None, &[], &sites);
+        assert_eq!(site.register(&sites_desc), Err(Error::DuplicateLabelName));
+
+        // Nor may they be those that metrics label their own samples with
+        let le = [Label::new("le", LabelValue::Str("x"))];
+        let quantile = [Label::new("quantile", LabelValue::Str("x"))];
+        assert_eq!(
+            Registry::new().set_labels(&le),
+            Err(Error::InvalidLabelName)
+        );
+        assert_eq!(
+            Registry::new().set_labels(&quantile),
+            Err(Error::InvalidLabelName)
+        );
+        let build = metrics::info::Info::new([("site", "west")]);
+        let build_desc = MetricDesc::new("build", "Build", None, &[], &build);
+        assert_eq!(
+            registry.register(&build_desc),
+            Err(Error::DuplicateLabelName)
+        );
+        let latency = metrics::histogram::Histogram::new(&[10]);
+        let latency_desc = MetricDesc::new("latency", "Latency", None, &["le"], &latency);
+        assert_eq!(
+            registry.register(&latency_desc),
+            Err(Error::DuplicateLabelName)
+        );
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        registry.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE requests counter
+# HELP requests Requests
+requests_total{device_id="0a1b",site="north"} 0
+# EOF
+"#
+        );
+
+        // Storage is claimed by the first registry to set its labels
+        let other_registry = Registry::new().with_label_storage(&storage);
+        assert_eq!(
+            other_registry.set_labels(&[Label::new("site", LabelValue::Str("east"))]),
+            Err(Error::LabelsAlreadySet)
+        );
+        assert_eq!(
+            Registry::new().set_labels(&[Label::new("site", LabelValue::Str("east"))]),
+            Err(Error::NoLabelStorage)
+        );
+    }
+
+    #[test]
+    fn composite() {
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::{counter::Counter, gauge::Gauge},
+        };
+
+        static APP_REGISTRY: Registry = Registry::new();
+        static LIBRARY_REGISTRY: Registry =
+            Registry::new().with_labels(&[Label::new("library", LabelValue::Str("net"))]);
+        static REGISTRY: CompositeRegistry =
+            CompositeRegistry::new(&[&APP_REGISTRY, &LIBRARY_REGISTRY]);
+
+        static COUNTERS: [Counter; 2] = [const { Counter::new() }; 2];
+        static APP_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[0]);
+        static LIBRARY_DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests", None, &[], &COUNTERS[1]);
+        static TEMPERATURE: Gauge = Gauge::new();
+        static TEMPERATURE_DESC: MetricDesc =
+            MetricDesc::new("temperature", "Temperature", None, &[], &TEMPERATURE);
+
+        APP_REGISTRY.register(&APP_DESC).unwrap();
+        LIBRARY_REGISTRY.register(&LIBRARY_DESC).unwrap();
+        LIBRARY_REGISTRY.register(&TEMPERATURE_DESC).unwrap();
+
+        assert!(ptr::eq(REGISTRY.get("requests").unwrap(), &APP_DESC));
+
+        let mut buf = [0; 512];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        let expected = encoder.into_inner().written().to_vec();
+        assert_eq!(
+            core::str::from_utf8(&expected).unwrap(),
+            r#"# TYPE requests counter
+# HELP requests Requests
+requests_total 0
+# TYPE temperature gauge
+# HELP temperature Temperature
+temperature{library="net"} 0
+# EOF
+"#
+        );
+
+        let mut buf = [0; 100];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        let mut cursor = Cursor::new();
+        let mut chunks = Vec::new();
+        while !cursor.is_done() {
+            REGISTRY.encode_from(&mut cursor, &mut encoder).unwrap();
+            chunks.extend_from_slice(encoder.sink().written());
+            encoder.sink_mut().clear();
+        }
+        assert_eq!(chunks, expected);
+    }
+
+    #[test]
+    fn validation() {
+        static METRIC: metrics::gauge::Gauge = metrics::gauge::Gauge::new();
+
+        fn try_new(
+            name: &'static str,
+            unit: Option<&'static str>,
+            labels: &'static [&'static str],
+        ) -> Result<(), Error> {
+            MetricDesc::try_new(name, "Some metric", unit, labels, &METRIC).map(|_| ())
+        }
+
+        assert_eq!(try_new("some_metric:rate5m", None, &["some_label"]), Ok(()));
+        assert_eq!(try_new("_2xx", None, &["_2xx"]), Ok(()));
+        assert_eq!(try_new("", None, &[]), Err(Error::InvalidName));
+        assert_eq!(try_new("2xx", None, &[]), Err(Error::InvalidName));
+        assert_eq!(try_new("some-metric", None, &[]), Err(Error::InvalidName));
+        assert_eq!(
+            try_new("requests_total", None, &[]),
+            Err(Error::ReservedSuffix)
+        );
+        assert_eq!(
+            try_new("latency_bucket", None, &[]),
+            Err(Error::ReservedSuffix)
+        );
+        assert_eq!(
+            try_new("requests_created", None, &[]),
+            Err(Error::ReservedSuffix)
+        );
+        assert_eq!(
+            try_new("latency_sum", None, &[]),
+            Err(Error::ReservedSuffix)
+        );
+        assert_eq!(
+            try_new("latency_count", None, &[]),
+            Err(Error::ReservedSuffix)
+        );
+        assert_eq!(try_new("latency_seconds", Some("seconds"), &[]), Ok(()));
+        assert_eq!(
+            try_new("latency_seconds", Some(""), &[]),
+            Err(Error::InvalidUnit)
+        );
+        assert_eq!(
+            try_new("latency_seconds", Some("onds"), &[]),
+            Err(Error::InvalidUnit)
+        );
+        assert_eq!(
+            try_new("seconds", Some("seconds"), &[]),
+            Err(Error::InvalidUnit)
+        );
+        assert_eq!(
+            try_new("latency", Some("seconds"), &[]),
+            Err(Error::InvalidUnit)
+        );
+        assert_eq!(try_new("a", None, &[""]), Err(Error::InvalidLabelName));
+        assert_eq!(try_new("a", None, &["a:b"]), Err(Error::InvalidLabelName));
+        assert_eq!(
+            try_new("a", None, &["__name"]),
+            Err(Error::InvalidLabelName)
+        );
+        assert_eq!(
+            try_new("a", None, &["ok", "0k"]),
+            Err(Error::InvalidLabelName)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid metric name")]
+    fn validation_at_runtime() {
+        static METRIC: metrics::gauge::Gauge = metrics::gauge::Gauge::new();
+        let name = String::from("some-metric");
+        MetricDesc::new(name.as_str(), "Some metric", None, &[], &METRIC);
+    }
+
+    #[test]
+    fn unregistration() {
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::gauge::Gauge,
+        };
+
+        static REGISTRY: Registry = Registry::new();
+
+        static A: Gauge = Gauge::new();
+        static A_DESC: MetricDesc = MetricDesc::new("a", "A", None, &[], &A);
+        static B: Gauge = Gauge::new();
+        static B_DESC: MetricDesc = MetricDesc::new("b", "B", None, &[], &B);
+        static C: Gauge = Gauge::new();
+        static C_DESC: MetricDesc = MetricDesc::new("c", "C", None, &[], &C);
+
+        fn names(output: &[u8]) -> String {
+            core::str::from_utf8(output)
+                .unwrap()
+                .lines()
+                .filter_map(|line| line.strip_prefix("# TYPE "))
+                .map(|line| &line[..1])
+                .collect()
+        }
+
+        fn encoded_names() -> String {
+            let mut buf = [0; 256];
+            let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+            REGISTRY.encode(&mut encoder).unwrap();
+            names(encoder.into_inner().written())
+        }
+
+        REGISTRY.register(&A_DESC).unwrap();
+        REGISTRY.register(&B_DESC).unwrap();
+        REGISTRY.register(&C_DESC).unwrap();
+        assert_eq!(encoded_names(), "cba");
+
+        assert!(REGISTRY.unregister(&B_DESC));
+        assert!(!REGISTRY.unregister(&B_DESC));
+        assert_eq!(encoded_names(), "ca");
+
+        assert!(REGISTRY.unregister(&C_DESC));
+        assert_eq!(encoded_names(), "a");
+
+        REGISTRY.register(&B_DESC).unwrap();
+        REGISTRY.register(&C_DESC).unwrap();
+        assert_eq!(encoded_names(), "cba");
+
+        // Registering again between chunks neither repeats nor skips
+        let mut buf = [0; 40];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        let mut cursor = Cursor::new();
+        let mut chunks = Vec::new();
+        while !cursor.is_done() {
+            REGISTRY.encode_from(&mut cursor, &mut encoder).unwrap();
+            chunks.extend_from_slice(encoder.sink().written());
+            encoder.sink_mut().clear();
+            for desc in [&C_DESC, &B_DESC] {
+                assert!(REGISTRY.unregister(desc));
+                REGISTRY.register(desc).unwrap();
+            }
+        }
+        assert_eq!(names(&chunks), "cba");
+    }
+
+    #[test]
+    fn unregistration_while_encoding() {
+        use std::sync::atomic::AtomicBool;
+
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::gauge::Gauge,
+        };
+
+        static REGISTRY: Registry = Registry::new();
+
+        static GAUGES: [Gauge; 4] = [const { Gauge::new() }; 4];
+        static DESCS: [MetricDesc; 4] = [
+            MetricDesc::new("g0", "Gauge", None, &[], &GAUGES[0]),
+            MetricDesc::new("g1", "Gauge", None, &[], &GAUGES[1]),
+            MetricDesc::new("g2", "Gauge", None, &[], &GAUGES[2]),
+            MetricDesc::new("g3", "Gauge", None, &[], &GAUGES[3]),
+        ];
+        static DONE: AtomicBool = AtomicBool::new(false);
+
+        for desc in &DESCS {
+            REGISTRY.register(desc).unwrap();
+        }
+
+        std::thread::scope(|scope| {
+            let togglers: Vec<_> = (1..DESCS.len())
+                .map(|i| {
+                    scope.spawn(move || {
+                        for _ in 0..10_000 {
+                            assert!(REGISTRY.unregister(&DESCS[i]));
+                            REGISTRY.register(&DESCS[i]).unwrap();
+                        }
+                    })
+                })
+                .collect();
+            let encoding = scope.spawn(|| {
+                let mut encodings = 0;
+                while !DONE.load(Ordering::Relaxed) {
+                    let mut buf = [0; 1024];
+                    let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+                    REGISTRY.encode(&mut encoder).unwrap();
+                    let sink = encoder.into_inner();
+                    let output = core::str::from_utf8(sink.written()).unwrap();
+                    // The descriptor never unregistered is always encoded,
+                    // and no descriptor is encoded twice
+                    assert!(output.contains("# TYPE g0 gauge\n"));
+                    assert!(output.ends_with("# EOF\n"));
+                    for desc in &DESCS {
+                        let type_line = format!("# TYPE {} gauge\n", desc.name);
+                        assert!(output.matches(&type_line).count() <= 1);
+                    }
+                    encodings += 1;
+                }
+                encodings
+            });
+            for toggler in togglers {
+                toggler.join().unwrap();
+            }
+            DONE.store(true, Ordering::Relaxed);
+            assert!(encoding.join().unwrap() > 0);
+        });
+
+        let mut buf = [0; 1024];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        let sink = encoder.into_inner();
+        let output = core::str::from_utf8(sink.written()).unwrap();
+        assert_eq!(output.matches("# TYPE").count(), DESCS.len());
+    }
+
+    #[test]
+    fn created() {
+        use crate::encoders::{text::TextEncoder, SliceSink};
+
+        struct MyClock(AtomicUsize);
+        impl Clock for MyClock {
+            fn now(&self) -> u64 {
+                self.0.fetch_add(1000, Ordering::Relaxed) as u64
+            }
+        }
+
+        static CLOCK: MyClock = MyClock(AtomicUsize::new(1_000_000));
+        static REGISTRY: Registry = Registry::new().with_clock(&CLOCK);
+
+        counter!(static REQUESTS, REGISTRY, "requests", "Requests received");
+
+        REQUESTS.inc();
+        assert_eq!(REQUESTS.created(), Some(1_000_000));
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE requests counter
+# HELP requests Requests received
+requests_total 1
+requests_created 1000.000
+# EOF
+"#
+        );
+
+        REQUESTS.reset();
+        assert_eq!(REQUESTS.created(), None);
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE requests counter
+# HELP requests Requests received
+requests_total 0
+requests_created 1002.000
+# EOF
+"#
+        );
+
+        REQUESTS.reset_at(REGISTRY.now());
+        assert_eq!(REQUESTS.created(), Some(1_003_000));
+    }
+
+    #[cfg(feature = "linkme")]
+    #[test]
+    fn linked() {
+        use crate::{
+            encoders::{text::TextEncoder, SliceSink},
+            metrics::counter::Counter,
+        };
+
+        static REGISTRY: Registry = Registry::linked();
+
+        metric!(
+            static LINKED: Counter = Counter::new();
+            section: METRICS,
+            name: "linked",
+            help: "Collected by the linker",
+        );
+
+        counter!(static REGISTERED, REGISTRY, "registered", "Registered at runtime");
+
+        LINKED.inc();
+        REGISTERED.inc();
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE registered counter
+# HELP registered Registered at runtime
+registered_total 1
+# TYPE linked counter
+# HELP linked Collected by the linker
+linked_total 1
+# EOF
+"#
+        );
     }
 }
@@ -0,0 +1,221 @@
+//! Macros for declaring metrics
+
+/// Declares a metric as a static along with its descriptor. The metric
+/// registers itself with the given registry when first used, or when its
+/// `register` method is called.
+///
+/// Alternatively, with the `linkme` feature, `section: METRICS` may be
+/// given in place of a registry so that the descriptor is collected by
+/// the linker into `METRICS`. The metric is then declared
+/// as a plain static, and requires no registration.
+///
+/// ```
+/// use discreet_metrics::{metric, metrics::histogram::Histogram, Registry};
+///
+/// static REGISTRY: Registry = Registry::new();
+///
+/// metric!(
+///     /// Time taken to service a request
+///     pub static LATENCY: Histogram<3> = Histogram::new(&[10, 100, 1000]);
+///     registry: REGISTRY,
+///     name: "latency_milliseconds",
+///     help: "Request latency",
+///     unit: "milliseconds",
+/// );
+///
+/// LATENCY.observe(42);
+/// ```
+#[macro_export]
+macro_rules! metric {
+    (
+        $(#[$attr:meta])*
+        $vis:vis static $ident:ident: $ty:ty = $init:expr;
+        registry: $registry:path,
+        name: $name:expr,
+        help: $help:expr
+        $(, unit: $unit:expr)?
+        $(, labels: $labels:expr)?
+        $(,)?
+    ) => {
+        $(#[$attr])*
+        $vis static $ident: $crate::Registered<'static, $ty> = {
+            static METRIC: $ty = $init;
+            static DESC: $crate::MetricDesc<'static> = $crate::MetricDesc::new(
+                $name,
+                $help,
+                $crate::metric!(@unit $($unit)?),
+                $crate::metric!(@labels $($labels)?),
+                &METRIC,
+            );
+            $crate::Registered::new(&METRIC, &DESC, &$registry)
+        };
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis static $ident:ident: $ty:ty = $init:expr;
+        section: $section:path,
+        name: $name:expr,
+        help: $help:expr
+        $(, unit: $unit:expr)?
+        $(, labels: $labels:expr)?
+        $(,)?
+    ) => {
+        $(#[$attr])*
+        $vis static $ident: $ty = $init;
+        const _: () = {
+            static DESC: $crate::MetricDesc<'static> = $crate::MetricDesc::new(
+                $name,
+                $help,
+                $crate::metric!(@unit $($unit)?),
+                $crate::metric!(@labels $($labels)?),
+                &$ident,
+            );
+            #[$crate::linkme::distributed_slice($section)]
+            #[linkme(crate = $crate::linkme)]
+            static ENTRY: &$crate::MetricDesc<'static> = &DESC;
+        };
+    };
+    (@unit) => { None };
+    (@unit $unit:expr) => { Some($unit) };
+    (@labels) => { &[] };
+    (@labels $labels:expr) => { &$labels };
+}
+
+/// Declares a [Counter](crate::metrics::counter::Counter) as a static
+/// along with its descriptor. See [metric!].
+///
+/// ```
+/// use discreet_metrics::{counter, Registry};
+///
+/// static REGISTRY: Registry = Registry::new();
+///
+/// counter!(pub static REQUESTS, REGISTRY, "requests", "Requests received");
+///
+/// REQUESTS.inc();
+/// ```
+#[macro_export]
+macro_rules! counter {
+    ($(#[$attr:meta])* $vis:vis static $ident:ident, $registry:path, $name:expr, $help:expr $(,)?) => {
+        $crate::metric!(
+            $(#[$attr])*
+            $vis static $ident: $crate::metrics::counter::Counter =
+                $crate::metrics::counter::Counter::new();
+            registry: $registry,
+            name: $name,
+            help: $help,
+        );
+    };
+}
+
+/// Declares a [Gauge](crate::metrics::gauge::Gauge) as a static
+/// along with its descriptor. See [metric!].
+///
+/// ```
+/// use discreet_metrics::{gauge, Registry};
+///
+/// static REGISTRY: Registry = Registry::new();
+///
+/// gauge!(pub static QUEUE_DEPTH, REGISTRY, "queue_depth", "Items queued");
+///
+/// QUEUE_DEPTH.set(3);
+/// ```
+#[macro_export]
+macro_rules! gauge {
+    ($(#[$attr:meta])* $vis:vis static $ident:ident, $registry:path, $name:expr, $help:expr $(,)?) => {
+        $crate::metric!(
+            $(#[$attr])*
+            $vis static $ident: $crate::metrics::gauge::Gauge =
+                $crate::metrics::gauge::Gauge::new();
+            registry: $registry,
+            name: $name,
+            help: $help,
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encoders::{text::TextEncoder, SliceSink},
+        metrics::counter::Counter,
+        metrics::family::{Family, OnFull},
+        Registry,
+    };
+
+    static REGISTRY: Registry = Registry::new();
+    static OTHER_REGISTRY: Registry = Registry::new();
+
+    counter!(static REQUESTS, REGISTRY, "requests", "Requests received");
+
+    metric!(
+        static RESPONSES: Family<u16, Counter, 2> =
+            Family::new([const { Counter::new() }; 2], OnFull::Reject);
+        registry: REGISTRY,
+        name: "responses",
+        help: "Responses sent",
+        labels: ["code"],
+    );
+
+    counter!(static RETRIES, OTHER_REGISTRY, "retries", "Requests retried");
+    counter!(static RETRIED, OTHER_REGISTRY, "retries", "Requests retried");
+
+    #[test]
+    fn registers_when_first_used() {
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(encoder.into_inner().written(), b"# EOF\n");
+
+        REQUESTS.inc();
+        REQUESTS.inc();
+        RESPONSES.get_or_insert(200).unwrap().inc();
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE responses counter
+# HELP responses Responses sent
+responses_total{code="200"} 1
+# TYPE requests counter
+# HELP requests Requests received
+requests_total 2
+# EOF
+"#
+        );
+    }
+
+    #[test]
+    fn remains_unregistered_when_used() {
+        fn encoded() -> String {
+            let mut buf = [0; 256];
+            let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+            OTHER_REGISTRY.encode(&mut encoder).unwrap();
+            String::from_utf8(encoder.into_inner().written().to_vec()).unwrap()
+        }
+
+        RETRIES.inc();
+        assert!(OTHER_REGISTRY.unregister(RETRIES.desc()));
+        RETRIES.inc();
+        assert_eq!(encoded(), "# EOF\n");
+
+        RETRIES.register().unwrap();
+        assert_eq!(
+            encoded(),
+            "# TYPE retries counter\n\
+             # HELP retries Requests retried\n\
+             retries_total 2\n\
+             # EOF\n"
+        );
+
+        // A metric rejected on first use is not registered again on use,
+        // even once its name is free
+        RETRIED.inc();
+        assert!(OTHER_REGISTRY.unregister(RETRIES.desc()));
+        RETRIED.inc();
+        assert_eq!(encoded(), "# EOF\n");
+        RETRIED.register().unwrap();
+        assert!(encoded().contains("retries_total 2\n"));
+    }
+}
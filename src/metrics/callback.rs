@@ -0,0 +1,113 @@
+//! Metrics whose values are provided by a function called when they are
+//! encoded, for values that are cheapest to obtain only when required, such
+//! as free memory or uptime. Nothing is stored by these metrics.
+
+use crate::{Encoder, Error, Metric, MetricType, Sample, Value};
+
+/// A gauge whose value is returned by a function when encoded.
+pub struct CallbackGauge<'a> {
+    value: &'a (dyn Fn() -> i64 + Sync),
+}
+
+impl<'a> CallbackGauge<'a> {
+    /// Create a gauge given a function returning its value e.g.
+    /// `CallbackGauge::new(&|| free_heap() as i64)`.
+    pub const fn new(value: &'a (dyn Fn() -> i64 + Sync)) -> Self {
+        Self { value }
+    }
+
+    /// Return the current value
+    pub fn value(&self) -> i64 {
+        (self.value)()
+    }
+}
+
+impl Metric for CallbackGauge<'_> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Gauge
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new("", Value::Signed(self.value())))
+    }
+}
+
+/// A counter whose total is returned by a function when encoded. The
+/// function must return a total that only ever increases, other than
+/// when reset to zero.
+pub struct CallbackCounter<'a> {
+    total: &'a (dyn Fn() -> u64 + Sync),
+}
+
+impl<'a> CallbackCounter<'a> {
+    /// Create a counter given a function returning its total e.g.
+    /// `CallbackCounter::new(&|| uptime_seconds())`.
+    pub const fn new(total: &'a (dyn Fn() -> u64 + Sync)) -> Self {
+        Self { total }
+    }
+
+    /// Return the current total
+    pub fn total(&self) -> u64 {
+        (self.total)()
+    }
+}
+
+impl Metric for CallbackCounter<'_> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new("_total", Value::Unsigned(self.total())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    use crate::{
+        encoders::{text::TextEncoder, SliceSink},
+        MetricDesc, Registry,
+    };
+
+    use super::*;
+
+    #[test]
+    fn evaluated_when_encoded() {
+        static CALLS: AtomicU64 = AtomicU64::new(0);
+
+        fn calls() -> u64 {
+            CALLS.fetch_add(1, Ordering::Relaxed) + 1
+        }
+
+        static REGISTRY: Registry = Registry::new();
+
+        static CALLED: CallbackCounter = CallbackCounter::new(&calls);
+        static CALLED_DESC: MetricDesc =
+            MetricDesc::new("called", "Times called", None, &[], &CALLED);
+
+        static OFFSET: CallbackGauge =
+            CallbackGauge::new(&|| -(CALLS.load(Ordering::Relaxed) as i64));
+        static OFFSET_DESC: MetricDesc =
+            MetricDesc::new("offset", "Negated calls", None, &[], &OFFSET);
+
+        REGISTRY.register(&OFFSET_DESC).unwrap();
+        REGISTRY.register(&CALLED_DESC).unwrap();
+
+        for expected in [
+            "# TYPE called counter\n# HELP called Times called\ncalled_total 1\n\
+             # TYPE offset gauge\n# HELP offset Negated calls\noffset -1\n# EOF\n",
+            "# TYPE called counter\n# HELP called Times called\ncalled_total 2\n\
+             # TYPE offset gauge\n# HELP offset Negated calls\noffset -2\n# EOF\n",
+        ] {
+            let mut buf = [0; 256];
+            let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+            REGISTRY.encode(&mut encoder).unwrap();
+            assert_eq!(
+                core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+                expected
+            );
+        }
+    }
+}
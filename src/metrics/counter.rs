@@ -4,16 +4,26 @@
 //! CPU seconds spent, or bytes sent. For counters how quickly they are increasing over time
 //! is what is of interest to a user.
 
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{any::Any, sync::atomic::Ordering};
 
+use crate::{
+    atomic::AtomicUsize, clock::AtomicTimestamp, is_label_name, Encoder, Error, Metric, MetricType,
+    Sample, Value,
+};
+
+use super::exemplar::{ExemplarEncoder, ExemplarSlot};
+
+#[derive(Default)]
 pub struct Counter {
     total: AtomicUsize,
+    created: AtomicTimestamp,
 }
 
 impl Counter {
     pub const fn new() -> Self {
         Self {
             total: AtomicUsize::new(0),
+            created: AtomicTimestamp::new(),
         }
     }
 
@@ -31,4 +41,179 @@ impl Counter {
     pub fn total(&self) -> usize {
         self.total.load(Ordering::Relaxed)
     }
+
+    /// Return the time the counter was created or last reset, in
+    /// milliseconds since the Unix epoch, as given by a registry's clock
+    pub fn created(&self) -> Option<u64> {
+        self.created.load()
+    }
+
+    /// Reset the total to zero. The time of the reset is given by a
+    /// registry's clock when the counter is next encoded.
+    pub fn reset(&self) {
+        self.reset_at(None);
+    }
+
+    /// Reset the total to zero at a time given in milliseconds since the
+    /// Unix epoch e.g. `counter.reset_at(registry.now())`, so that the
+    /// time is known before the counter is next encoded.
+    pub fn reset_at(&self, now: Option<u64>) {
+        self.total.store(0, Ordering::Relaxed);
+        self.created.store(now);
+    }
+}
+
+impl Metric for Counter {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new("_total", Value::Unsigned(self.total() as u64)))?;
+        encode_created(self.created(), enc)
+    }
+
+    fn stamp(&self, now: u64) {
+        if self.created.load().is_none() {
+            self.created.store(Some(now));
+        }
+    }
+}
+
+pub(crate) fn encode_created(created: Option<u64>, enc: &mut dyn Encoder) -> Result<(), Error> {
+    match created {
+        Some(created) => enc.write_sample(&Sample::new(
+            "_created",
+            Value::Decimal {
+                value: created as i64,
+                places: 3,
+            },
+        )),
+        None => Ok(()),
+    }
+}
+
+/// A counter that retains the last exemplar, having an id of `L` bytes,
+/// recorded along with an increment.
+pub struct ExemplarCounter<const L: usize> {
+    counter: Counter,
+    label: &'static str,
+    exemplar: ExemplarSlot<L>,
+}
+
+impl<const L: usize> ExemplarCounter<L> {
+    /// Create a counter given the name of the label for its exemplar ids
+    /// e.g. "trace_id", panicking if the name is invalid.
+    pub const fn new(label: &'static str) -> Self {
+        if !is_label_name(label.as_bytes()) {
+            panic!("{}", Error::InvalidLabelName.as_str());
+        }
+        Self {
+            counter: Counter::new(),
+            label,
+            exemplar: ExemplarSlot::new(),
+        }
+    }
+
+    /// Add one to the counter
+    pub fn inc(&self) {
+        self.counter.inc();
+    }
+
+    /// Add a number of counts to the counter
+    pub fn inc_by(&self, count: usize) {
+        self.counter.inc_by(count);
+    }
+
+    /// Add one to the counter and record an exemplar given its id and
+    /// an optional timestamp in milliseconds since the Unix epoch
+    pub fn inc_with_exemplar(&self, id: &[u8; L], timestamp: Option<u64>) {
+        self.inc_by_with_exemplar(1, id, timestamp);
+    }
+
+    /// Add a number of counts to the counter and record an exemplar given
+    /// its id and an optional timestamp in milliseconds since the Unix epoch
+    pub fn inc_by_with_exemplar(&self, count: usize, id: &[u8; L], timestamp: Option<u64>) {
+        self.counter.inc_by(count);
+        self.exemplar.record(id, count, timestamp);
+    }
+
+    /// Return the current total
+    pub fn total(&self) -> usize {
+        self.counter.total()
+    }
+
+    /// Return the time the counter was created or last reset. See
+    /// [Counter::created].
+    pub fn created(&self) -> Option<u64> {
+        self.counter.created()
+    }
+
+    /// Reset the total to zero. See [Counter::reset].
+    pub fn reset(&self) {
+        self.counter.reset();
+    }
+
+    /// Reset the total to zero at a given time. See [Counter::reset_at].
+    pub fn reset_at(&self, now: Option<u64>) {
+        self.counter.reset_at(now);
+    }
+}
+
+impl<const L: usize> Metric for ExemplarCounter<L> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        self.counter.encode(&mut ExemplarEncoder {
+            inner: enc,
+            suffix: "_total",
+            label: self.label,
+            exemplars: core::iter::once(&self.exemplar),
+        })
+    }
+
+    fn stamp(&self, now: u64) {
+        self.counter.stamp(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encoders::text::encode_desc, MetricDesc};
+
+    use super::*;
+
+    #[test]
+    fn exemplar() {
+        static REQUESTS: ExemplarCounter<4> = ExemplarCounter::new("trace_id");
+        static DESC: MetricDesc =
+            MetricDesc::new("requests", "Requests received", None, &[], &REQUESTS);
+
+        REQUESTS.inc();
+        REQUESTS.inc_by_with_exemplar(2, &[0xde, 0xad, 0xbe, 0xef], Some(1520879607789));
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE requests counter
+# HELP requests Requests received
+requests_total 3 # {trace_id="deadbeef"} 2 1520879607.789
+"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid label name")]
+    fn invalid_exemplar_label_name() {
+        ExemplarCounter::<4>::new("trace-id");
+    }
 }
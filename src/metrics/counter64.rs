@@ -0,0 +1,104 @@
+//! Counters with 64 bit totals. Targets without 64 bit atomics require
+//! either the `portable-atomic` or the `critical-section` feature.
+
+use core::{any::Any, sync::atomic::Ordering};
+
+use crate::{
+    atomic::AtomicU64, clock::AtomicTimestamp, Encoder, Error, Metric, MetricType, Sample, Value,
+};
+
+use super::counter::encode_created;
+
+/// A counter with a 64 bit total regardless of the target's word size,
+/// for counts that would otherwise wrap, such as bytes sent.
+pub struct Counter64 {
+    total: AtomicU64,
+    created: AtomicTimestamp,
+}
+
+impl Counter64 {
+    pub const fn new() -> Self {
+        Self {
+            total: AtomicU64::new(0),
+            created: AtomicTimestamp::new(),
+        }
+    }
+
+    /// Add one to the counter
+    pub fn inc(&self) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add a number of counts to the counter
+    pub fn inc_by(&self, count: u64) {
+        self.total.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Return the current total
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Return the time the counter was created or last reset. See
+    /// [Counter::created](super::counter::Counter::created).
+    pub fn created(&self) -> Option<u64> {
+        self.created.load()
+    }
+
+    /// Reset the total to zero. See [Counter::reset](super::counter::Counter::reset).
+    pub fn reset(&self) {
+        self.reset_at(None);
+    }
+
+    /// Reset the total to zero at a given time. See [Counter::reset_at](super::counter::Counter::reset_at).
+    pub fn reset_at(&self, now: Option<u64>) {
+        self.total.store(0, Ordering::Relaxed);
+        self.created.store(now);
+    }
+}
+
+impl Default for Counter64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metric for Counter64 {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Counter
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new("_total", Value::Unsigned(self.total())))?;
+        encode_created(self.created(), enc)
+    }
+
+    fn stamp(&self, now: u64) {
+        if self.created.load().is_none() {
+            self.created.store(Some(now));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter64_beyond_32_bits() {
+        let counter = Counter64::new();
+        counter.inc_by(u32::MAX as u64);
+        counter.inc();
+        assert_eq!(counter.total(), 1 << 32);
+
+        counter.reset_at(Some(1_000));
+        assert_eq!(counter.total(), 0);
+        assert_eq!(counter.created(), Some(1_000));
+        counter.reset();
+        assert_eq!(counter.created(), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Storage for the exemplars of metrics such as counters and histograms.
+//!
+//! Each exemplar is held in a fixed-size slot that retains the last exemplar
+//! recorded: an id of `L` bytes, such as a trace id, along with a value and
+//! an optional timestamp. Slots are guarded by a sequence number so that
+//! an exemplar can be recorded from a thread or an interrupt while being
+//! encoded elsewhere. Neither recording nor encoding ever waits: an exemplar
+//! recorded while another is being recorded to the same slot is dropped, and
+//! an exemplar changing while being encoded is omitted from that encoding.
+
+use core::sync::atomic::Ordering;
+
+use crate::{
+    atomic::{AtomicBool, AtomicU8, AtomicUsize},
+    seqlock::{Halves, SeqLock},
+    Encoder, Error, Exemplar, Label, LabelValue, MetricDesc, Sample, Value,
+};
+
+/// An exemplar as recorded in a slot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Recorded<const L: usize> {
+    pub id: [u8; L],
+    pub value: usize,
+    pub timestamp: Option<u64>,
+}
+
+/// Retains the last exemplar recorded, having an id of `L` bytes.
+pub struct ExemplarSlot<const L: usize> {
+    lock: SeqLock,
+    recorded: AtomicBool,
+    id: [AtomicU8; L],
+    value: AtomicUsize,
+    timestamped: AtomicBool,
+    timestamp: Halves,
+}
+
+impl<const L: usize> Default for ExemplarSlot<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const L: usize> ExemplarSlot<L> {
+    /// Create an empty slot
+    pub const fn new() -> Self {
+        Self {
+            lock: SeqLock::new(),
+            recorded: AtomicBool::new(false),
+            id: [const { AtomicU8::new(0) }; L],
+            value: AtomicUsize::new(0),
+            timestamped: AtomicBool::new(false),
+            timestamp: Halves::new(),
+        }
+    }
+
+    /// Record an exemplar, replacing any previous one
+    pub fn record(&self, id: &[u8; L], value: usize, timestamp: Option<u64>) {
+        self.lock.write(|| {
+            for (to, from) in self.id.iter().zip(id) {
+                to.store(*from, Ordering::Relaxed);
+            }
+            self.value.store(value, Ordering::Relaxed);
+            self.timestamped
+                .store(timestamp.is_some(), Ordering::Relaxed);
+            self.timestamp.store(timestamp.unwrap_or_default());
+            self.recorded.store(true, Ordering::Relaxed);
+        });
+    }
+
+    /// Return the last exemplar recorded, if there is one and it is not
+    /// being recorded to.
+    pub fn load(&self) -> Option<Recorded<L>> {
+        self.lock
+            .read(|| {
+                if !self.recorded.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let mut id = [0; L];
+                for (to, from) in id.iter_mut().zip(&self.id) {
+                    *to = from.load(Ordering::Relaxed);
+                }
+                Some(Recorded {
+                    id,
+                    value: self.value.load(Ordering::Relaxed),
+                    timestamp: self
+                        .timestamped
+                        .load(Ordering::Relaxed)
+                        .then(|| self.timestamp.load()),
+                })
+            })
+            .flatten()
+    }
+}
+
+/// Attaches exemplars, in turn, to each of the samples with a given
+/// suffix. Exemplar ids are labelled with the given label name.
+pub(crate) struct ExemplarEncoder<'e, 'a, I> {
+    pub(crate) inner: &'e mut dyn Encoder<'a>,
+    pub(crate) suffix: &'static str,
+    pub(crate) label: &'static str,
+    pub(crate) exemplars: I,
+}
+
+impl<'a, 's, I, const L: usize> Encoder<'a> for ExemplarEncoder<'_, 'a, I>
+where
+    I: Iterator<Item = &'s ExemplarSlot<L>>,
+{
+    fn write_desc(&mut self, desc: &'a MetricDesc<'a>) -> Result<(), Error> {
+        self.inner.write_desc(desc)
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> Result<(), Error> {
+        if sample.suffix != self.suffix {
+            return self.inner.write_sample(sample);
+        }
+        match self.exemplars.next().and_then(ExemplarSlot::load) {
+            Some(recorded) => {
+                let labels = [Label::new(self.label, LabelValue::Hex(&recorded.id))];
+                let exemplar = Exemplar::new(
+                    &labels,
+                    Value::Unsigned(recorded.value as u64),
+                    recorded.timestamp,
+                );
+                self.inner.write_sample(&sample.with_exemplar(exemplar))
+            }
+            None => self.inner.write_sample(sample),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.inner.write(bytes)
+    }
+
+    fn write_eof(&mut self) -> Result<(), Error> {
+        self.inner.write_eof()
+    }
+
+    fn position(&self) -> usize {
+        self.inner.position()
+    }
+
+    fn rewind(&mut self, position: usize) {
+        self.inner.rewind(position)
+    }
+
+    fn set_constant_labels(&mut self, labels: &'a [Label<'a>]) {
+        self.inner.set_constant_labels(labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_load() {
+        let slot: ExemplarSlot<2> = ExemplarSlot::new();
+        assert_eq!(slot.load(), None);
+
+        slot.record(&[0xab, 0x01], 3, Some(u32::MAX as u64 + 1));
+        assert_eq!(
+            slot.load(),
+            Some(Recorded {
+                id: [0xab, 0x01],
+                value: 3,
+                timestamp: Some(u32::MAX as u64 + 1)
+            })
+        );
+
+        slot.record(&[0xcd, 0x02], 4, None);
+        assert_eq!(
+            slot.load(),
+            Some(Recorded {
+                id: [0xcd, 0x02],
+                value: 4,
+                timestamp: None
+            })
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! From OpenMetrics:
+//!
+//! A MetricFamily may have zero or more Metrics. Every Metric within a
+//! MetricFamily must have a unique LabelSet.
+//!
+//! A family retains a fixed number of child metrics, each identified by the
+//! values of the labels named by the family's descriptor. Children are
+//! assigned to label values as they are first looked up, and are never
+//! released.
+
+use core::{any::Any, cell::UnsafeCell, hint, mem::MaybeUninit, sync::atomic::Ordering};
+
+use crate::{
+    atomic::AtomicU8, Encoder, Error, Label, LabelValue, Metric, MetricDesc, MetricType, Sample,
+};
+
+/// The label values identifying a child metric of a family. Label values
+/// are provided in the same order as the label names of the family's
+/// descriptor.
+pub trait LabelSet: Copy + PartialEq {
+    /// Call a function with the label values
+    fn with_values<R>(&self, f: impl FnOnce(&[LabelValue]) -> R) -> R;
+}
+
+macro_rules! label_set_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl LabelSet for $t {
+                fn with_values<R>(&self, f: impl FnOnce(&[LabelValue]) -> R) -> R {
+                    f(&[(*self).into()])
+                }
+            }
+        )*
+    };
+}
+
+label_set_scalar!(
+    &'static str,
+    u8,
+    u16,
+    u32,
+    u64,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    isize
+);
+
+macro_rules! label_set_tuple {
+    ($($name:ident),*) => {
+        impl<$($name),*> LabelSet for ($($name,)*)
+        where
+            $($name: Copy + PartialEq + Into<LabelValue<'static>>),*
+        {
+            #[allow(non_snake_case)]
+            fn with_values<R>(&self, f: impl FnOnce(&[LabelValue]) -> R) -> R {
+                let ($($name,)*) = *self;
+                f(&[$($name.into()),*])
+            }
+        }
+    };
+}
+
+label_set_tuple!(A);
+label_set_tuple!(A, B);
+label_set_tuple!(A, B, C);
+label_set_tuple!(A, B, C, D);
+
+/// What to do when looking up label values that have no child metric
+/// and there is no room left for another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnFull<K> {
+    /// The lookup fails.
+    Reject,
+    /// The last child metric is reserved for the given label values,
+    /// and is returned for any label values that have no room.
+    Overflow(K),
+}
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const READY: u8 = 2;
+
+pub struct Family<K, M, const N: usize> {
+    states: [AtomicU8; N],
+    keys: [UnsafeCell<MaybeUninit<K>>; N],
+    metrics: [M; N],
+    on_full: OnFull<K>,
+}
+
+// Safety: a key is written only by the thread that claims its empty slot,
+// and is read only once the slot has been published as ready.
+unsafe impl<K, M, const N: usize> Sync for Family<K, M, N>
+where
+    K: Send + Sync,
+    M: Sync,
+{
+}
+
+impl<K, M, const N: usize> Family<K, M, N>
+where
+    K: LabelSet,
+{
+    /// Create a family given its child metrics, which are typically
+    /// declared as `[const { Counter::new() }; N]`.
+    pub const fn new(metrics: [M; N], on_full: OnFull<K>) -> Self {
+        assert!(N > 0, "A family must have at least one child metric");
+        let mut states = [const { AtomicU8::new(EMPTY) }; N];
+        let mut keys = [const { UnsafeCell::new(MaybeUninit::uninit()) }; N];
+        if let OnFull::Overflow(key) = on_full {
+            states[N - 1] = AtomicU8::new(READY);
+            keys[N - 1] = UnsafeCell::new(MaybeUninit::new(key));
+        }
+        Self {
+            states,
+            keys,
+            metrics,
+            on_full,
+        }
+    }
+
+    /// Return the child metric for the given label values, if there is one
+    pub fn get(&self, key: K) -> Option<&M> {
+        (0..N)
+            .find(|i| self.key(*i) == Some(key))
+            .map(|i| &self.metrics[i])
+    }
+
+    /// Return the child metric for the given label values, assigning
+    /// label values to a child if they have not been seen before. When
+    /// there are no more children then the family's [OnFull] behaviour
+    /// applies.
+    ///
+    /// Assigning label values to a child is brief, but a lookup that
+    /// encounters a child being assigned will wait for it. A lookup that
+    /// interrupts the assignment would wait forever, and so interrupt
+    /// handlers must use [Family::try_get_or_insert] instead.
+    pub fn get_or_insert(&self, key: K) -> Option<&M> {
+        self.lookup(key, true)
+    }
+
+    /// Return the child metric for the given label values as per
+    /// [Family::get_or_insert], but without ever waiting. A lookup that
+    /// encounters a child being assigned is treated as though there are no
+    /// more children, and so the family's [OnFull] behaviour applies.
+    pub fn try_get_or_insert(&self, key: K) -> Option<&M> {
+        self.lookup(key, false)
+    }
+
+    fn lookup(&self, key: K, wait: bool) -> Option<&M> {
+        // The overflow child is reserved for its label values already
+        if matches!(self.on_full, OnFull::Overflow(k) if k == key) {
+            return Some(&self.metrics[N - 1]);
+        }
+        'children: for i in 0..N {
+            loop {
+                match self.states[i].load(Ordering::Acquire) {
+                    READY => {
+                        if self.key(i) == Some(key) {
+                            return Some(&self.metrics[i]);
+                        }
+                        break;
+                    }
+                    EMPTY => {
+                        if self.states[i]
+                            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+                            .is_ok()
+                        {
+                            unsafe { (*self.keys[i].get()).write(key) };
+                            self.states[i].store(READY, Ordering::Release);
+                            return Some(&self.metrics[i]);
+                        }
+                    }
+                    _ if wait => hint::spin_loop(),
+                    _ => break 'children,
+                }
+            }
+        }
+        match self.on_full {
+            OnFull::Reject => None,
+            OnFull::Overflow(_) => Some(&self.metrics[N - 1]),
+        }
+    }
+
+    fn key(&self, i: usize) -> Option<K> {
+        (self.states[i].load(Ordering::Acquire) == READY)
+            .then(|| unsafe { (*self.keys[i].get()).assume_init() })
+    }
+}
+
+impl<K, M, const N: usize> Metric for Family<K, M, N>
+where
+    K: LabelSet + Send + Sync + 'static,
+    M: Metric + Sync + 'static,
+{
+    fn metric_type(&self) -> MetricType {
+        self.metrics[0].metric_type()
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        for (i, metric) in self.metrics.iter().enumerate() {
+            if let Some(key) = self.key(i) {
+                key.with_values(|label_values| {
+                    metric.encode(&mut LabelledEncoder {
+                        inner: &mut *enc,
+                        label_values,
+                    })
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn stamp(&self, now: u64) {
+        for (i, metric) in self.metrics.iter().enumerate() {
+            if self.key(i).is_some() {
+                metric.stamp(now);
+            }
+        }
+    }
+
+    fn has_label(&self, name: &str) -> bool {
+        self.metrics[0].has_label(name)
+    }
+}
+
+/// Applies the label values of a child metric to each of its samples.
+struct LabelledEncoder<'e, 'a, 'v> {
+    inner: &'e mut dyn Encoder<'a>,
+    label_values: &'v [LabelValue<'v>],
+}
+
+impl<'a> Encoder<'a> for LabelledEncoder<'_, 'a, '_> {
+    fn write_desc(&mut self, desc: &'a MetricDesc<'a>) -> Result<(), Error> {
+        self.inner.write_desc(desc)
+    }
+
+    fn write_sample(&mut self, sample: &Sample) -> Result<(), Error> {
+        self.inner
+            .write_sample(&sample.with_label_values(self.label_values))
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.inner.write(bytes)
+    }
+
+    fn write_eof(&mut self) -> Result<(), Error> {
+        self.inner.write_eof()
+    }
+
+    fn position(&self) -> usize {
+        self.inner.position()
+    }
+
+    fn rewind(&mut self, position: usize) {
+        self.inner.rewind(position)
+    }
+
+    fn set_constant_labels(&mut self, labels: &'a [Label<'a>]) {
+        self.inner.set_constant_labels(labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encoders::text::encode_desc,
+        metrics::{counter::Counter, histogram::Histogram},
+    };
+
+    use super::*;
+
+    #[test]
+    fn lookup_or_insert() {
+        let family: Family<(&str, u16), Counter, 2> =
+            Family::new([const { Counter::new() }; 2], OnFull::Reject);
+
+        assert!(family.get(("GET", 200)).is_none());
+        family.get_or_insert(("GET", 200)).unwrap().inc();
+        family.get_or_insert(("GET", 200)).unwrap().inc();
+        family.get_or_insert(("PUT", 500)).unwrap().inc();
+        assert!(family.get_or_insert(("PUT", 200)).is_none());
+
+        assert_eq!(family.get(("GET", 200)).unwrap().total(), 2);
+        assert_eq!(family.get(("PUT", 500)).unwrap().total(), 1);
+    }
+
+    #[test]
+    fn overflow() {
+        let family: Family<&str, Counter, 2> =
+            Family::new([const { Counter::new() }; 2], OnFull::Overflow("other"));
+
+        assert!(family.get("other").is_some());
+        family.get_or_insert("a").unwrap().inc();
+        family.get_or_insert("b").unwrap().inc();
+        family.get_or_insert("c").unwrap().inc();
+
+        assert_eq!(family.get("a").unwrap().total(), 1);
+        assert!(family.get("b").is_none());
+        assert_eq!(family.get("other").unwrap().total(), 2);
+    }
+
+    #[test]
+    fn overflow_looked_up_directly() {
+        let family: Family<&str, Counter, 3> =
+            Family::new([const { Counter::new() }; 3], OnFull::Overflow("other"));
+
+        family.get_or_insert("other").unwrap().inc();
+        family.try_get_or_insert("other").unwrap().inc();
+        family.get_or_insert("a").unwrap().inc();
+        family.get_or_insert("b").unwrap().inc();
+        family.get_or_insert("c").unwrap().inc();
+
+        assert_eq!(family.get("a").unwrap().total(), 1);
+        assert_eq!(family.get("b").unwrap().total(), 1);
+        assert_eq!(family.get("other").unwrap().total(), 3);
+        assert_eq!(
+            (0..3)
+                .filter_map(|i| family.key(i))
+                .filter(|k| *k == "other")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn lookup_without_waiting() {
+        let family: Family<&str, Counter, 3> =
+            Family::new([const { Counter::new() }; 3], OnFull::Overflow("other"));
+
+        family.try_get_or_insert("a").unwrap().inc();
+
+        // As though interrupting the assignment of a child
+        family.states[1].store(WRITING, Ordering::Relaxed);
+        family.try_get_or_insert("a").unwrap().inc();
+        family.try_get_or_insert("b").unwrap().inc();
+        assert_eq!(family.get("a").unwrap().total(), 2);
+        assert!(family.get("b").is_none());
+        assert_eq!(family.get("other").unwrap().total(), 1);
+
+        let family: Family<&str, Counter, 2> =
+            Family::new([const { Counter::new() }; 2], OnFull::Reject);
+        family.states[0].store(WRITING, Ordering::Relaxed);
+        assert!(family.try_get_or_insert("a").is_none());
+    }
+
+    #[test]
+    fn encode() {
+        static FAMILY: Family<(&str, u16), Histogram<1>, 3> =
+            Family::new([const { Histogram::new(&[100]) }; 3], OnFull::Reject);
+        static DESC: MetricDesc = MetricDesc::new(
+            "latency",
+            "Request latency",
+            None,
+            &["method", "code"],
+            &FAMILY,
+        );
+
+        FAMILY.get_or_insert(("GET", 200)).unwrap().observe(10);
+        FAMILY.get_or_insert(("PUT", 404)).unwrap().observe(1000);
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE latency histogram
+# HELP latency Request latency
+latency_bucket{method="GET",code="200",le="100"} 1
+latency_bucket{method="GET",code="200",le="+Inf"} 1
+latency_sum{method="GET",code="200"} 10
+latency_count{method="GET",code="200"} 1
+latency_bucket{method="PUT",code="404",le="100"} 0
+latency_bucket{method="PUT",code="404",le="+Inf"} 1
+latency_sum{method="PUT",code="404"} 1000
+latency_count{method="PUT",code="404"} 1
+"#
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! From OpenTelemetry:
+//!
+//! Gauges are current measurements, such as bytes of memory currently used or the number
+//! of items in a queue. For gauges the absolute value is what is of interest to a user.
+
+use core::{any::Any, sync::atomic::Ordering};
+
+use crate::{atomic::AtomicIsize, Encoder, Error, Metric, MetricType, Sample, Value};
+
+#[derive(Default)]
+pub struct Gauge {
+    value: AtomicIsize,
+}
+
+impl Gauge {
+    pub const fn new() -> Self {
+        Self {
+            value: AtomicIsize::new(0),
+        }
+    }
+
+    /// Set the gauge to a value
+    pub fn set(&self, value: isize) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    /// Add one to the gauge
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Subtract one from the gauge
+    pub fn dec(&self) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Add an amount to the gauge
+    pub fn inc_by(&self, amount: isize) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Subtract an amount from the gauge
+    pub fn dec_by(&self, amount: isize) {
+        self.value.fetch_sub(amount, Ordering::Relaxed);
+    }
+
+    /// Return the current value
+    pub fn value(&self) -> isize {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl Metric for Gauge {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Gauge
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new("", Value::Signed(self.value() as i64)))
+    }
+}
+
+/// A gauge of fixed-point values for targets without floating point
+/// support. Values are held as integers in units of `10^-PLACES`
+/// e.g. millivolts for volts given 3 places, and are encoded as decimals.
+pub struct FixedGauge<const PLACES: u8> {
+    value: AtomicIsize,
+}
+
+/// A fixed-point gauge in thousandths
+pub type MilliGauge = FixedGauge<3>;
+
+/// A fixed-point gauge in millionths
+pub type MicroGauge = FixedGauge<6>;
+
+impl<const PLACES: u8> FixedGauge<PLACES> {
+    pub const fn new() -> Self {
+        assert!(PLACES <= 19, "A fixed-point gauge has at most 19 places");
+        Self {
+            value: AtomicIsize::new(0),
+        }
+    }
+
+    /// Set the gauge to a value in units of `10^-PLACES`
+    pub fn set(&self, value: isize) {
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    /// Add an amount in units of `10^-PLACES` to the gauge
+    pub fn inc_by(&self, amount: isize) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Subtract an amount in units of `10^-PLACES` from the gauge
+    pub fn dec_by(&self, amount: isize) {
+        self.value.fetch_sub(amount, Ordering::Relaxed);
+    }
+
+    /// Return the current value in units of `10^-PLACES`
+    pub fn value(&self) -> isize {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+impl<const PLACES: u8> Default for FixedGauge<PLACES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const PLACES: u8> Metric for FixedGauge<PLACES> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Gauge
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new(
+            "",
+            Value::Decimal {
+                value: self.value() as i64,
+                places: PLACES,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encoders::text::encode_desc, MetricDesc};
+
+    use super::*;
+
+    #[test]
+    fn up_and_down() {
+        let gauge = Gauge::new();
+        gauge.inc();
+        gauge.inc_by(10);
+        gauge.dec();
+        gauge.dec_by(3);
+        assert_eq!(gauge.value(), 7);
+        gauge.set(-2);
+        assert_eq!(gauge.value(), -2);
+    }
+
+    #[test]
+    fn fixed_point() {
+        static VOLTAGE: MilliGauge = MilliGauge::new();
+        static DESC: MetricDesc = MetricDesc::new(
+            "supply_volts",
+            "Supply voltage",
+            Some("volts"),
+            &[],
+            &VOLTAGE,
+        );
+
+        VOLTAGE.set(3300);
+        VOLTAGE.dec_by(295);
+        assert_eq!(VOLTAGE.value(), 3005);
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE supply_volts gauge
+# HELP supply_volts Supply voltage
+# UNIT supply_volts volts
+supply_volts 3.005
+"#
+        );
+    }
+}
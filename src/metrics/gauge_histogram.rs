@@ -0,0 +1,110 @@
+//! From OpenMetrics:
+//!
+//! GaugeHistograms measure current distributions. Common examples are how
+//! long items have been waiting in a queue, or size of the requests in a
+//! queue.
+//!
+//! As with histograms, the number of buckets is fixed at compile time.
+//! Values are added to and removed from their buckets as the distribution
+//! changes, and so each removal must correspond to a value previously
+//! added.
+
+use core::any::Any;
+
+use crate::{Encoder, Error, Metric, MetricType, Sample, Value};
+
+use super::histogram::Buckets;
+
+pub struct GaugeHistogram<const N: usize> {
+    buckets: Buckets<N>,
+}
+
+impl<const N: usize> GaugeHistogram<N> {
+    /// Create a gauge histogram given the inclusive upper bound of each
+    /// bucket. Bounds must be strictly increasing.
+    pub const fn new(bounds: &[usize; N]) -> Self {
+        Self {
+            buckets: Buckets::new(bounds),
+        }
+    }
+
+    /// Add a value to its bucket
+    pub fn inc(&self, value: usize) {
+        self.buckets.add(value);
+    }
+
+    /// Remove a value, previously added, from its bucket
+    pub fn dec(&self, value: usize) {
+        self.buckets.remove(value);
+    }
+
+    /// Return the number of values currently held
+    pub fn count(&self) -> usize {
+        self.buckets.count()
+    }
+
+    /// Return the sum of the values currently held
+    pub fn sum(&self) -> usize {
+        self.buckets.sum()
+    }
+}
+
+impl<const N: usize> Metric for GaugeHistogram<N> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::GaugeHistogram
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn has_label(&self, name: &str) -> bool {
+        name == "le"
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        let count = self.buckets.encode(enc)?;
+        enc.write_sample(&Sample::new("_gcount", Value::Unsigned(count as u64)))?;
+        enc.write_sample(&Sample::new("_gsum", Value::Unsigned(self.sum() as u64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encoders::text::encode_desc, MetricDesc};
+
+    use super::*;
+
+    #[test]
+    fn up_and_down() {
+        static QUEUED: GaugeHistogram<2> = GaugeHistogram::new(&[10, 100]);
+        static DESC: MetricDesc = MetricDesc::new(
+            "queued_milliseconds",
+            "Time spent queued",
+            Some("milliseconds"),
+            &[],
+            &QUEUED,
+        );
+
+        QUEUED.inc(5);
+        QUEUED.inc(50);
+        QUEUED.inc(500);
+        QUEUED.dec(50);
+
+        assert_eq!(QUEUED.count(), 2);
+        assert_eq!(QUEUED.sum(), 505);
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE queued_milliseconds gaugehistogram
+# HELP queued_milliseconds Time spent queued
+# UNIT queued_milliseconds milliseconds
+queued_milliseconds_bucket{le="10"} 1
+queued_milliseconds_bucket{le="100"} 1
+queued_milliseconds_bucket{le="+Inf"} 2
+queued_milliseconds_gcount 2
+queued_milliseconds_gsum 505
+"#
+        );
+    }
+}
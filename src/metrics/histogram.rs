@@ -0,0 +1,318 @@
+//! From OpenTelemetry:
+//!
+//! Histograms measure distributions of values, such as request latencies or
+//! response sizes. Observations are counted into buckets along with a total
+//! of all of the values observed.
+//!
+//! The number of buckets is fixed at compile time so that no allocation is
+//! required. Observations greater than the last bucket's upper bound are
+//! counted only within the implicit `+Inf` bucket.
+
+use core::{any::Any, sync::atomic::Ordering};
+
+use crate::{
+    atomic::AtomicUsize, is_label_name, Encoder, Error, Label, LabelValue, Metric, MetricType,
+    Sample, Value,
+};
+
+use super::exemplar::{ExemplarEncoder, ExemplarSlot};
+
+/// The buckets of a histogram or gauge histogram, along with the sum of
+/// the values counted within them.
+pub(crate) struct Buckets<const N: usize> {
+    bounds: [usize; N],
+    buckets: [AtomicUsize; N],
+    overflow: AtomicUsize,
+    sum: AtomicUsize,
+}
+
+impl<const N: usize> Buckets<N> {
+    /// Panics unless the bounds are strictly increasing
+    pub(crate) const fn new(bounds: &[usize; N]) -> Self {
+        let mut i = 1;
+        while i < N {
+            assert!(
+                bounds[i - 1] < bounds[i],
+                "Histogram bounds must be strictly increasing"
+            );
+            i += 1;
+        }
+        Self {
+            bounds: *bounds,
+            buckets: [const { AtomicUsize::new(0) }; N],
+            overflow: AtomicUsize::new(0),
+            sum: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count a value into its bucket
+    pub(crate) fn add(&self, value: usize) {
+        self.bucket(value).fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Remove a value, previously added, from its bucket
+    pub(crate) fn remove(&self, value: usize) {
+        self.bucket(value).fetch_sub(1, Ordering::Relaxed);
+        self.sum.fetch_sub(value, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.buckets
+            .iter()
+            .fold(self.overflow.load(Ordering::Relaxed), |count, bucket| {
+                count + bucket.load(Ordering::Relaxed)
+            })
+    }
+
+    pub(crate) fn sum(&self) -> usize {
+        self.sum.load(Ordering::Relaxed)
+    }
+
+    /// The index of a value's bucket, or none for the `+Inf` bucket
+    pub(crate) fn position(&self, value: usize) -> Option<usize> {
+        self.bounds.iter().position(|bound| value <= *bound)
+    }
+
+    fn bucket(&self, value: usize) -> &AtomicUsize {
+        match self.position(value) {
+            Some(i) => &self.buckets[i],
+            None => &self.overflow,
+        }
+    }
+
+    /// Write the cumulative `_bucket` samples, returning the total count
+    pub(crate) fn encode(&self, enc: &mut dyn Encoder) -> Result<usize, Error> {
+        let mut cumulative = 0;
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let labels = [Label::new("le", LabelValue::Unsigned(*bound as u64))];
+            enc.write_sample(
+                &Sample::new("_bucket", Value::Unsigned(cumulative as u64)).with_labels(&labels),
+            )?;
+        }
+        cumulative += self.overflow.load(Ordering::Relaxed);
+        let labels = [Label::new("le", LabelValue::Str("+Inf"))];
+        enc.write_sample(
+            &Sample::new("_bucket", Value::Unsigned(cumulative as u64)).with_labels(&labels),
+        )?;
+        Ok(cumulative)
+    }
+}
+
+pub struct Histogram<const N: usize> {
+    buckets: Buckets<N>,
+}
+
+impl<const N: usize> Histogram<N> {
+    /// Create a histogram given the inclusive upper bound of each bucket.
+    /// Bounds must be strictly increasing.
+    pub const fn new(bounds: &[usize; N]) -> Self {
+        Self {
+            buckets: Buckets::new(bounds),
+        }
+    }
+
+    /// Record a value
+    pub fn observe(&self, value: usize) {
+        self.buckets.add(value);
+    }
+
+    /// Return the number of values observed
+    pub fn count(&self) -> usize {
+        self.buckets.count()
+    }
+
+    /// Return the sum of all values observed
+    pub fn sum(&self) -> usize {
+        self.buckets.sum()
+    }
+}
+
+impl<const N: usize> Metric for Histogram<N> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Histogram
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn has_label(&self, name: &str) -> bool {
+        name == "le"
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        let count = self.buckets.encode(enc)?;
+        enc.write_sample(&Sample::new("_sum", Value::Unsigned(self.sum() as u64)))?;
+        enc.write_sample(&Sample::new("_count", Value::Unsigned(count as u64)))
+    }
+}
+
+/// A histogram that retains the last exemplar, having an id of `L` bytes,
+/// recorded within each bucket.
+pub struct ExemplarHistogram<const N: usize, const L: usize> {
+    histogram: Histogram<N>,
+    label: &'static str,
+    exemplars: [ExemplarSlot<L>; N],
+    overflow: ExemplarSlot<L>,
+}
+
+impl<const N: usize, const L: usize> ExemplarHistogram<N, L> {
+    /// Create a histogram given the inclusive upper bound of each bucket,
+    /// and the name of the label for its exemplar ids e.g. "trace_id",
+    /// panicking if the name is invalid. Bounds must be strictly
+    /// increasing.
+    pub const fn new(bounds: &[usize; N], label: &'static str) -> Self {
+        if !is_label_name(label.as_bytes()) {
+            panic!("{}", Error::InvalidLabelName.as_str());
+        }
+        Self {
+            histogram: Histogram::new(bounds),
+            label,
+            exemplars: [const { ExemplarSlot::new() }; N],
+            overflow: ExemplarSlot::new(),
+        }
+    }
+
+    /// Record a value
+    pub fn observe(&self, value: usize) {
+        self.histogram.observe(value);
+    }
+
+    /// Record a value along with an exemplar given its id and an optional
+    /// timestamp in milliseconds since the Unix epoch
+    pub fn observe_with_exemplar(&self, value: usize, id: &[u8; L], timestamp: Option<u64>) {
+        self.histogram.observe(value);
+        match self.histogram.buckets.position(value) {
+            Some(i) => &self.exemplars[i],
+            None => &self.overflow,
+        }
+        .record(id, value, timestamp);
+    }
+
+    /// Return the number of values observed
+    pub fn count(&self) -> usize {
+        self.histogram.count()
+    }
+
+    /// Return the sum of all values observed
+    pub fn sum(&self) -> usize {
+        self.histogram.sum()
+    }
+}
+
+impl<const N: usize, const L: usize> Metric for ExemplarHistogram<N, L> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Histogram
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn has_label(&self, name: &str) -> bool {
+        self.histogram.has_label(name)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        self.histogram.encode(&mut ExemplarEncoder {
+            inner: enc,
+            suffix: "_bucket",
+            label: self.label,
+            exemplars: self.exemplars.iter().chain([&self.overflow]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encoders::text::encode_desc, MetricDesc};
+
+    use super::*;
+
+    #[test]
+    fn observations() {
+        static HISTOGRAM: Histogram<3> = Histogram::new(&[10, 100, 1000]);
+
+        HISTOGRAM.observe(5);
+        HISTOGRAM.observe(10);
+        HISTOGRAM.observe(50);
+        HISTOGRAM.observe(5000);
+
+        assert_eq!(HISTOGRAM.count(), 4);
+        assert_eq!(HISTOGRAM.sum(), 5065);
+
+        struct MyEncoder(Vec<(String, Vec<String>, Value)>);
+        impl Encoder<'_> for MyEncoder {
+            fn write_desc(&mut self, _desc: &crate::MetricDesc) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn write_sample(&mut self, sample: &Sample) -> Result<(), Error> {
+                let labels = sample
+                    .labels
+                    .iter()
+                    .map(|l| format!("{}={:?}", l.name, l.value))
+                    .collect();
+                self.0
+                    .push((sample.suffix.to_string(), labels, sample.value));
+                Ok(())
+            }
+
+            fn write(&mut self, _bytes: &[u8]) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn write_eof(&mut self) -> Result<(), Error> {
+                Ok(())
+            }
+
+            fn position(&self) -> usize {
+                self.0.len()
+            }
+
+            fn rewind(&mut self, position: usize) {
+                self.0.truncate(position);
+            }
+        }
+
+        let mut encoder = MyEncoder(Vec::new());
+        assert!(HISTOGRAM.encode(&mut encoder).is_ok());
+        let values: Vec<_> = encoder.0.iter().map(|(_, _, v)| *v).collect();
+        assert_eq!(values, [2, 3, 3, 4, 5065, 4].map(Value::Unsigned).to_vec());
+        assert_eq!(encoder.0[0].1, ["le=Unsigned(10)"]);
+        assert_eq!(encoder.0[3].1, ["le=Str(\"+Inf\")"]);
+        assert_eq!(encoder.0[4].0, "_sum");
+        assert_eq!(encoder.0[5].0, "_count");
+    }
+
+    #[test]
+    fn exemplars() {
+        static LATENCY: ExemplarHistogram<2, 1> = ExemplarHistogram::new(&[10, 100], "trace_id");
+        static DESC: MetricDesc =
+            MetricDesc::new("latency", "Request latency", None, &[], &LATENCY);
+
+        LATENCY.observe(5);
+        LATENCY.observe_with_exemplar(50, &[0x01], None);
+        LATENCY.observe_with_exemplar(500, &[0x02], Some(1000));
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE latency histogram
+# HELP latency Request latency
+latency_bucket{le="10"} 1
+latency_bucket{le="100"} 2 # {trace_id="01"} 50
+latency_bucket{le="+Inf"} 3 # {trace_id="02"} 500 1.000
+latency_sum 555
+latency_count 3
+"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid label name")]
+    fn invalid_exemplar_label_name() {
+        ExemplarHistogram::<1, 1>::new(&[10], "__trace_id");
+    }
+}
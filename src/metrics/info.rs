@@ -0,0 +1,86 @@
+//! From OpenMetrics:
+//!
+//! Info metrics are used to expose textual information which SHOULD NOT
+//! change during process lifetime. Common examples are an application's
+//! version, revision control commit, and the version of a compiler.
+
+use core::any::Any;
+
+use crate::{
+    check_label_names, Encoder, Error, Label, LabelValue, Metric, MetricType, Sample, Value,
+};
+
+/// Information given as label names and values that are fixed at compile
+/// time.
+pub struct Info<const N: usize> {
+    labels: [Label<'static>; N],
+}
+
+impl<const N: usize> Info<N> {
+    /// Create the information given its label names and values e.g.
+    /// `Info::new([("version", env!("CARGO_PKG_VERSION"))])`, panicking if
+    /// any of the names are invalid or the same.
+    pub const fn new(labels: [(&'static str, &'static str); N]) -> Self {
+        let mut info = [Label::new("", LabelValue::Str("")); N];
+        let mut i = 0;
+        while i < N {
+            let (name, value) = labels[i];
+            info[i] = Label::new(name, LabelValue::Str(value));
+            i += 1;
+        }
+        if let Err(e) = check_label_names(&info) {
+            panic!("{}", e.as_str());
+        }
+        Self { labels: info }
+    }
+
+    /// Return the label names and values
+    pub fn labels(&self) -> &[Label<'static>] {
+        &self.labels
+    }
+}
+
+impl<const N: usize> Metric for Info<N> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Info
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn has_label(&self, name: &str) -> bool {
+        self.labels.iter().any(|label| label.name == name)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        enc.write_sample(&Sample::new("_info", Value::Unsigned(1)).with_labels(&self.labels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encoders::text::encode_desc, MetricDesc};
+
+    use super::*;
+
+    #[test]
+    fn encode() {
+        static BUILD: Info<2> = Info::new([("version", "1.2.3"), ("revision", "8f3e1a")]);
+        static DESC: MetricDesc = MetricDesc::new("build", "Build information", None, &[], &BUILD);
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE build info
+# HELP build Build information
+build_info{version="1.2.3",revision="8f3e1a"} 1
+"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid label name")]
+    fn invalid_label_name() {
+        Info::new([("__version", "1.2.3")]);
+    }
+}
@@ -1,3 +1,18 @@
 //! Various types of metrics as specified by OpenTelemetry
 
+pub mod callback;
 pub mod counter;
+#[cfg(any(
+    target_has_atomic = "64",
+    feature = "portable-atomic",
+    feature = "critical-section"
+))]
+pub mod counter64;
+pub mod exemplar;
+pub mod family;
+pub mod gauge;
+pub mod gauge_histogram;
+pub mod histogram;
+pub mod info;
+pub mod state_set;
+pub mod summary;
@@ -0,0 +1,148 @@
+//! From OpenMetrics:
+//!
+//! StateSets represent a series of related boolean values, also called a
+//! bitset. If ENUMs need to be encoded this MAY be done via StateSet.
+//!
+//! Each state is encoded as a sample labelled by the name of the metric,
+//! so a state set is encoded with the help of its descriptor. The name
+//! may not therefore contain colons, and is prefixed by that of any
+//! namespace.
+
+use core::{any::Any, sync::atomic::Ordering};
+
+use crate::{atomic::AtomicBool, Encoder, Error, LabelValue, Metric, MetricType, Sample, Value};
+
+/// A fixed set of named boolean states. States are identified by their
+/// index within the names given on creation, which is conveniently the
+/// discriminant of a fieldless enum.
+pub struct StateSet<const N: usize> {
+    names: [&'static str; N],
+    states: [AtomicBool; N],
+}
+
+impl<const N: usize> StateSet<N> {
+    /// Create a state set given the names of its states, all of which
+    /// are initially false.
+    pub const fn new(names: [&'static str; N]) -> Self {
+        Self {
+            names,
+            states: [const { AtomicBool::new(false) }; N],
+        }
+    }
+
+    /// Set the value of a state
+    pub fn set(&self, state: usize, value: bool) {
+        self.states[state].store(value, Ordering::Relaxed);
+    }
+
+    /// Set a state to true and all others to false, as for an enum
+    pub fn select(&self, state: usize) {
+        for (i, s) in self.states.iter().enumerate() {
+            s.store(i == state, Ordering::Relaxed);
+        }
+    }
+
+    /// Return the value of a state
+    pub fn get(&self, state: usize) -> bool {
+        self.states[state].load(Ordering::Relaxed)
+    }
+
+    /// Return the names of the states
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+}
+
+impl<const N: usize> Metric for StateSet<N> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::StateSet
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        for (name, state) in self.names.iter().zip(&self.states) {
+            let value = state.load(Ordering::Relaxed) as u64;
+            enc.write_sample(
+                &Sample::new("", Value::Unsigned(value)).with_name_label(LabelValue::Str(name)),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encoders::{
+            text::{encode_desc, TextEncoder},
+            SliceSink,
+        },
+        MetricDesc, Namespace, Registry,
+    };
+
+    use super::*;
+
+    #[test]
+    fn encode() {
+        enum Mode {
+            Idle,
+            Running,
+            Fault,
+        }
+
+        static MODE: StateSet<3> = StateSet::new(["idle", "running", "fault"]);
+        static DESC: MetricDesc = MetricDesc::new("mode", "Device mode", None, &[], &MODE);
+
+        MODE.select(Mode::Running as usize);
+        MODE.set(Mode::Fault as usize, true);
+        assert!(!MODE.get(Mode::Idle as usize));
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE mode stateset
+# HELP mode Device mode
+mode{mode="idle"} 0
+mode{mode="running"} 1
+mode{mode="fault"} 1
+"#
+        );
+    }
+
+    #[test]
+    fn encode_within_a_namespace() {
+        static REGISTRY: Registry = Registry::new();
+        static DEVICE: Namespace = Namespace::new(&REGISTRY, "device_");
+        static COLONS: Namespace = Namespace::new(&REGISTRY, "device:");
+
+        static MODE: StateSet<2> = StateSet::new(["idle", "running"]);
+        static DESC: MetricDesc = MetricDesc::new("mode", "Device mode", None, &[], &MODE);
+        static POWER: StateSet<1> = StateSet::new(["on"]);
+        static POWER_DESC: MetricDesc =
+            MetricDesc::new("power:state", "Power state", None, &[], &POWER);
+
+        assert_eq!(REGISTRY.register(&POWER_DESC), Err(Error::InvalidLabelName));
+        assert_eq!(COLONS.register(&DESC), Err(Error::InvalidLabelName));
+        DEVICE.register(&DESC).unwrap();
+
+        let mut buf = [0; 256];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        REGISTRY.encode(&mut encoder).unwrap();
+        assert_eq!(
+            core::str::from_utf8(encoder.into_inner().written()).unwrap(),
+            r#"# TYPE device_mode stateset
+# HELP device_mode Device mode
+device_mode{device_mode="idle"} 0
+device_mode{device_mode="running"} 0
+# EOF
+"#
+        );
+
+        // The states cannot be labelled without the descriptor
+        let mut buf = [0; 128];
+        let mut encoder = TextEncoder::new(SliceSink::new(&mut buf));
+        assert_eq!(MODE.encode(&mut encoder), Err(Error::InvalidLabelName));
+    }
+}
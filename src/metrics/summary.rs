@@ -0,0 +1,183 @@
+//! From OpenMetrics:
+//!
+//! Summaries also measure distributions of discrete events and MAY be used
+//! when Histograms are too expensive and/or an average event size is
+//! sufficient. They MAY also be used for backwards compatibility, because
+//! some existing instrumentation libraries expose precomputed quantiles.
+//!
+//! Quantiles are estimated over a sliding window of the most recent
+//! observations. The size of the window is fixed at compile time so that no
+//! allocation is required, and the window is sorted on the stack when the
+//! summary is encoded.
+
+use core::{any::Any, sync::atomic::Ordering};
+
+use crate::{
+    atomic::AtomicUsize, Encoder, Error, Label, LabelValue, Metric, MetricType, Sample, Value,
+};
+
+/// A summary of the most recent `W` observations with `Q` quantiles.
+pub struct Summary<const W: usize, const Q: usize> {
+    quantiles: [u16; Q],
+    window: [AtomicUsize; W],
+    count: AtomicUsize,
+    sum: AtomicUsize,
+}
+
+impl<const W: usize, const Q: usize> Summary<W, Q> {
+    /// Create a summary given its quantiles in thousandths e.g. 500 for
+    /// the median and 990 for the 99th percentile.
+    pub const fn new(quantiles: &[u16; Q]) -> Self {
+        assert!(W > 0, "A summary must have a window of at least one value");
+        let mut i = 0;
+        while i < Q {
+            assert!(
+                quantiles[i] <= 1000,
+                "Summary quantiles must be no more than 1000 thousandths"
+            );
+            i += 1;
+        }
+        Self {
+            quantiles: *quantiles,
+            window: [const { AtomicUsize::new(0) }; W],
+            count: AtomicUsize::new(0),
+            sum: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record a value, replacing the oldest value of the window once it
+    /// is full
+    pub fn observe(&self, value: usize) {
+        let i = self.count.fetch_add(1, Ordering::Relaxed) % W;
+        self.window[i].store(value, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Return the number of values observed
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Return the sum of all values observed
+    pub fn sum(&self) -> usize {
+        self.sum.load(Ordering::Relaxed)
+    }
+
+    /// Return the value of a quantile, given in thousandths, over the
+    /// window. There is no value until something has been observed.
+    pub fn quantile(&self, quantile: u16) -> Option<usize> {
+        let (window, len) = self.sorted_window();
+        Self::rank(&window[..len], quantile)
+    }
+
+    fn sorted_window(&self) -> ([usize; W], usize) {
+        let mut window = [0; W];
+        let len = self.count().min(W);
+        for (value, observed) in window.iter_mut().zip(&self.window[..len]) {
+            *value = observed.load(Ordering::Relaxed);
+        }
+        window[..len].sort_unstable();
+        (window, len)
+    }
+
+    /// The nearest-rank value of a quantile
+    fn rank(sorted: &[usize], quantile: u16) -> Option<usize> {
+        let rank = (sorted.len() * quantile as usize).div_ceil(1000);
+        sorted.get(rank.max(1) - 1).copied()
+    }
+}
+
+impl<const W: usize, const Q: usize> Metric for Summary<W, Q> {
+    fn metric_type(&self) -> MetricType {
+        MetricType::Summary
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+
+    fn has_label(&self, name: &str) -> bool {
+        name == "quantile"
+    }
+
+    fn encode(&self, enc: &mut dyn Encoder) -> Result<(), Error> {
+        let (window, len) = self.sorted_window();
+        for quantile in self.quantiles {
+            if let Some(value) = Self::rank(&window[..len], quantile) {
+                let labels = [Label::new("quantile", thousandths(quantile))];
+                enc.write_sample(
+                    &Sample::new("", Value::Unsigned(value as u64)).with_labels(&labels),
+                )?;
+            }
+        }
+        enc.write_sample(&Sample::new("_sum", Value::Unsigned(self.sum() as u64)))?;
+        enc.write_sample(&Sample::new("_count", Value::Unsigned(self.count() as u64)))
+    }
+}
+
+/// A label value of thousandths without trailing zeros e.g. "0.5" for 500
+fn thousandths(value: u16) -> LabelValue<'static> {
+    let mut value = value as i64;
+    let mut places = 3;
+    while places > 0 && value % 10 == 0 {
+        value /= 10;
+        places -= 1;
+    }
+    LabelValue::Decimal { value, places }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encoders::text::encode_desc, MetricDesc};
+
+    use super::*;
+
+    #[test]
+    fn sliding_window() {
+        let summary: Summary<4, 2> = Summary::new(&[500, 1000]);
+        assert_eq!(summary.quantile(500), None);
+
+        for value in [9, 1, 5, 7, 3, 2] {
+            summary.observe(value);
+        }
+
+        assert_eq!(summary.count(), 6);
+        assert_eq!(summary.sum(), 27);
+        assert_eq!(summary.quantile(0), Some(2));
+        assert_eq!(summary.quantile(500), Some(3));
+        assert_eq!(summary.quantile(750), Some(5));
+        assert_eq!(summary.quantile(1000), Some(7));
+    }
+
+    #[test]
+    fn encode() {
+        static LATENCY: Summary<8, 3> = Summary::new(&[500, 900, 990]);
+        static DESC: MetricDesc =
+            MetricDesc::new("latency", "Request latency", None, &[], &LATENCY);
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE latency summary
+# HELP latency Request latency
+latency_sum 0
+latency_count 0
+"#
+        );
+
+        for value in 1..=10 {
+            LATENCY.observe(value * 10);
+        }
+
+        assert_eq!(
+            encode_desc(&DESC),
+            r#"# TYPE latency summary
+# HELP latency Request latency
+latency{quantile="0.5"} 60
+latency{quantile="0.9"} 100
+latency{quantile="0.99"} 100
+latency_sum 550
+latency_count 10
+"#
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! A sequence lock guarding data held in several atomics, so that the data
+//! can be read and written as a whole on targets with only word-sized
+//! atomics, and from threads or interrupts alike.
+//!
+//! Neither reading nor writing ever waits: a write while another is in
+//! progress is dropped, and a read while a write is in progress, or that
+//! is overlapped by one, fails.
+
+use core::sync::atomic::{fence, Ordering};
+
+use crate::atomic::AtomicUsize;
+
+/// The sequence number of the data guarded, being odd while written
+#[derive(Default)]
+pub(crate) struct SeqLock {
+    seq: AtomicUsize,
+}
+
+impl SeqLock {
+    pub(crate) const fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+        }
+    }
+
+    /// Write the data guarded, unless it is being written already. The
+    /// data is to be written with relaxed stores.
+    pub(crate) fn write(&self, f: impl FnOnce()) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        if seq % 2 == 1
+            || self
+                .seq
+                .compare_exchange(
+                    seq,
+                    seq.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+        {
+            return;
+        }
+        fence(Ordering::Release);
+
+        f();
+
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Read the data guarded, unless it is being written. The data is to
+    /// be read with relaxed loads.
+    pub(crate) fn read<T>(&self, f: impl FnOnce() -> T) -> Option<T> {
+        let seq = self.seq.load(Ordering::Acquire);
+        if seq % 2 == 1 {
+            return None;
+        }
+
+        let value = f();
+
+        fence(Ordering::Acquire);
+        (self.seq.load(Ordering::Relaxed) == seq).then_some(value)
+    }
+}
+
+/// A 64 bit value held as two halves of 32 bits, to be guarded by a
+/// [SeqLock]
+#[derive(Default)]
+pub(crate) struct Halves([AtomicUsize; 2]);
+
+impl Halves {
+    pub(crate) const fn new() -> Self {
+        Self([const { AtomicUsize::new(0) }; 2])
+    }
+
+    pub(crate) fn store(&self, value: u64) {
+        self.0[0].store((value >> 32) as usize, Ordering::Relaxed);
+        self.0[1].store(value as u32 as usize, Ordering::Relaxed);
+    }
+
+    pub(crate) fn load(&self) -> u64 {
+        (self.0[0].load(Ordering::Relaxed) as u64) << 32 | self.0[1].load(Ordering::Relaxed) as u64
+    }
+}